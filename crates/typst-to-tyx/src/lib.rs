@@ -1,4 +1,9 @@
-//! Converts a Typst document to TyX elements, using `typlite`.
+//! Converts a Typst document to TyX elements.
+//!
+//! [`typst_syntax_to_tyx`] walks the syntax tree of the main file, keeping
+//! math, function calls and set rules, while [`typst_to_tyx`] goes through
+//! Markdown using `typlite`. [`markdown_to_tyx`] converts Markdown documents
+//! directly.
 //!
 //! ## Example
 //!
@@ -13,26 +18,48 @@
 //!     .expect("failed to resolve system universe");
 //!
 //! let world = verse.snapshot();
-//! let tyx_document = typst_to_tyx::typst_to_tyx(Arc::new(world));
+//! let tyx_document = typst_to_tyx::typst_syntax_to_tyx(Arc::new(world));
 //! ```
 use ecow::EcoString;
 pub use tinymist_project::LspWorld;
 
 use std::sync::Arc;
 use typlite::ast;
+use typst::World;
+
+mod markdown;
+mod syntax;
+
+pub use markdown::markdown_to_tyx;
+pub use syntax::typst_source_to_tyx;
 
 use tyx_schema::{self as s, TextFormat, TyXNode};
 
 fn plain(text: String) -> TyXNode {
     TyXNode::Text {
-        format: 0,
+        format: TextFormat::empty(),
         text,
         style: None,
     }
 }
 
-/// Converts the main document in a [`LspWorld`] to a [`TyXDocument`]
+/// Converts the main document in a [`LspWorld`] to a [`TyXDocument`] by walking
+/// its syntax tree, see [`typst_source_to_tyx`].
+pub fn typst_syntax_to_tyx(world: Arc<LspWorld>) -> Option<s::TyXDocument> {
+    let source = world.source(world.main_id()?).ok()?;
+    Some(typst_source_to_tyx(source.text()))
+}
+
+/// Converts the main document in a [`LspWorld`] to a [`TyXDocument`] through
+/// Markdown.
 pub fn typst_to_tyx(world: Arc<LspWorld>) -> Option<s::TyXDocument> {
+    // Restores the settings and preamble of files generated by TyX
+    let (settings, preamble) = world
+        .main_id()
+        .and_then(|id| world.source(id).ok())
+        .and_then(|source| syntax::embedded_settings(source.text()))
+        .unwrap_or_default();
+
     // Converts the source code into a markdown document
     let converter = typlite::Typlite::new(world);
     let md_doc = match converter.convert_doc(typlite::common::Format::Md) {
@@ -45,9 +72,6 @@ pub fn typst_to_tyx(world: Arc<LspWorld>) -> Option<s::TyXDocument> {
     // Gets the ast representation
     let node = md_doc.parse().ok()?;
 
-    // todo: more settings using `typst query`
-    let settings = Some(s::TyXDocumentSettings::default());
-
     // Generates the tyx output by walking the node
     let content = Converter.work(node);
     let NodeOptionOrList::Node(Some(content)) = content else {
@@ -57,11 +81,12 @@ pub fn typst_to_tyx(world: Arc<LspWorld>) -> Option<s::TyXDocument> {
     Some(s::TyXDocument {
         schema: Some("https://tyx-editor.com/schemas/tyx-document.schema.json".into()),
         version: tyx_version::VERSION.into(),
-        preamble: None,
+        preamble,
         content: Some(s::TyXDocumentContent { root: content }),
-        settings,
+        settings: Some(settings),
         filename: None,
         dirty: Some(false),
+        id: None,
     })
 }
 
@@ -157,27 +182,12 @@ impl Converter {
     }
 
     /// Applies the given text format to any child text nodes.
-    fn text_format(mask: u64, node: &mut TyXNode) {
+    fn text_format(mask: TextFormat, node: &mut TyXNode) {
         if let TyXNode::Text { format, .. } = node {
-            *format |= mask as i64;
+            *format |= mask;
         }
-        match node {
-            TyXNode::Root { children, .. }
-            | TyXNode::Paragraph { children, .. }
-            | TyXNode::List { children, .. }
-            | TyXNode::Listitem { children, .. }
-            | TyXNode::Quote { children, .. }
-            | TyXNode::Code { children, .. }
-            | TyXNode::Table { children, .. }
-            | TyXNode::Tablerow { children, .. }
-            | TyXNode::Tablecell { children, .. }
-            | TyXNode::Link { children, .. }
-            | TyXNode::Heading { children, .. } => {
-                for child in children.iter_mut() {
-                    Converter::text_format(mask, child);
-                }
-            }
-            _ => {}
+        for child in node.children_mut().into_iter().flatten() {
+            Converter::text_format(mask, child);
         }
     }
 
@@ -260,55 +270,80 @@ impl Converter {
 
     /// Converts an ordered list.
     fn ordered_list(&self, start: u32, items: Vec<ast::ListItem>) -> Option<TyXNode> {
-        let mut children = Vec::new();
-        for item in items {
-            if let Some(child) = self.list_item(item) {
-                children.push(child);
-            }
-        }
         Some(TyXNode::List {
-            children,
+            children: self.list_items(items),
             direction: None,
             list_type: s::TyXNodeListType::Number,
             start: start as i64,
         })
     }
 
-    /// Converts an unordered list.
+    /// Converts an unordered list, which is a check list if it only holds tasks.
     fn unordered_list(&self, list_items: Vec<ast::ListItem>) -> Option<TyXNode> {
-        // bulletList
-        let mut children = Vec::new();
-        for item in list_items {
-            if let Some(child) = self.list_item(item) {
-                children.push(child);
-            }
-        }
+        let list_type = if !list_items.is_empty()
+            && list_items
+                .iter()
+                .all(|item| matches!(item, ast::ListItem::Task { .. }))
+        {
+            s::TyXNodeListType::Check
+        } else {
+            s::TyXNodeListType::Bullet
+        };
 
         Some(TyXNode::List {
-            children,
+            children: self.list_items(list_items),
             direction: None,
-            list_type: s::TyXNodeListType::Bullet,
+            list_type,
             start: 0,
         })
     }
 
-    /// Converts a list item.
-    fn list_item(&self, item: ast::ListItem) -> Option<TyXNode> {
+    /// Converts list items.
+    fn list_items(&self, items: Vec<ast::ListItem>) -> Vec<TyXNode> {
+        items
+            .into_iter()
+            .flat_map(|item| self.list_item(item))
+            .collect()
+    }
+
+    /// Converts a list item. Since TyX list items can't hold both content and a
+    /// nested list, nested lists are moved to items of their own.
+    fn list_item(&self, item: ast::ListItem) -> Vec<TyXNode> {
         // todo: preserve id.
         let value = match &item {
             ast::ListItem::Ordered { number, .. } => *number,
             _ => None,
         };
+        let checked = match &item {
+            ast::ListItem::Task { status, .. } => {
+                Some(matches!(status, ast::TaskListStatus::Checked))
+            }
+            _ => None,
+        };
         let nodes = match item {
             ast::ListItem::Unordered { content, .. } => content,
             ast::ListItem::Ordered { content, .. } => content,
             ast::ListItem::Task { content, .. } => content,
         };
 
-        Some(TyXNode::Listitem {
-            children: self.children(nodes),
+        let (nested, children): (Vec<_>, Vec<_>) = self
+            .children(nodes)
+            .into_iter()
+            .partition(|child| matches!(child, TyXNode::List { .. }));
+        let mut result = Vec::new();
+        if !children.is_empty() || nested.is_empty() {
+            result.push(TyXNode::Listitem {
+                checked,
+                children,
+                value: value.unwrap_or(0) as i64,
+            });
+        }
+        result.extend(nested.into_iter().map(|list| TyXNode::Listitem {
+            checked: None,
+            children: vec![list],
             value: value.unwrap_or(0) as i64,
-        })
+        }));
+        result
     }
 
     /// Converts a table.
@@ -361,7 +396,7 @@ impl Converter {
     /// Converts an inline code.
     fn inline_code(&self, code: EcoString) -> Option<TyXNode> {
         let mut node = plain(code.into());
-        Converter::text_format(TextFormat::Code as u64, &mut node);
+        Converter::text_format(TextFormat::CODE, &mut node);
         Some(node)
     }
 
@@ -370,7 +405,7 @@ impl Converter {
         let mut worked = self.children(nodes);
         for node in worked.iter_mut() {
             // todo: we loss semantics here, assuming `emphasis` => `italic`.
-            Converter::text_format(TextFormat::Italic as u64, node);
+            Converter::text_format(TextFormat::ITALIC, node);
         }
         worked
     }
@@ -380,7 +415,7 @@ impl Converter {
         let mut worked = self.children(nodes);
         for node in worked.iter_mut() {
             // todo: we loss semantics here, assuming `strong` => `bold`.
-            Converter::text_format(TextFormat::Bold as u64, node);
+            Converter::text_format(TextFormat::BOLD, node);
         }
         worked
     }
@@ -389,7 +424,7 @@ impl Converter {
     fn strikethrough(&self, nodes: Vec<ast::Node>) -> Vec<TyXNode> {
         let mut worked = self.children(nodes);
         for node in worked.iter_mut() {
-            Converter::text_format(TextFormat::Strikethrough as u64, node);
+            Converter::text_format(TextFormat::STRIKETHROUGH, node);
         }
         worked
     }
@@ -0,0 +1,247 @@
+//! Converts Markdown to TyX by parsing it into the Markdown AST used by
+//! `typlite`, then reusing the [`Converter`](crate::Converter).
+use std::iter::Peekable;
+
+use pulldown_cmark::{Alignment, CodeBlockKind, Event, Options, Parser, Tag};
+use typlite::ast;
+use tyx_schema::{self as s, TyXNode};
+
+use crate::{Converter, NodeOptionOrList};
+
+/// Converts a Markdown document to a [`TyXDocument`](s::TyXDocument).
+///
+/// GitHub Flavored Markdown tables, task lists and strikethroughs are
+/// supported.
+pub fn markdown_to_tyx(text: &str) -> s::TyXDocument {
+    let options =
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let mut events = Parser::new_ext(text, options).peekable();
+    let document = ast::Node::Document(children(&mut events));
+
+    let mut root = match Converter.work(document) {
+        NodeOptionOrList::Node(Some(root)) => root,
+        _ => TyXNode::Root {
+            children: Vec::new(),
+            direction: None,
+        },
+    };
+    if let TyXNode::Root { children, .. } = &mut root
+        && children.is_empty()
+    {
+        children.push(TyXNode::Paragraph {
+            children: Vec::new(),
+            direction: None,
+            format: s::TyXNodeFormat::X,
+        });
+    }
+
+    s::TyXDocument {
+        schema: Some("https://tyx-editor.com/schemas/tyx-document.schema.json".into()),
+        version: tyx_version::VERSION.into(),
+        preamble: None,
+        content: Some(s::TyXDocumentContent { root }),
+        settings: Some(s::TyXDocumentSettings::default()),
+        filename: None,
+        dirty: Some(false),
+        id: None,
+    }
+}
+
+type Events<'a> = Peekable<Parser<'a>>;
+
+/// Appends a node, merging adjacent texts.
+fn push(nodes: &mut Vec<ast::Node>, node: ast::Node) {
+    if let ast::Node::Text(text) = &node
+        && let Some(ast::Node::Text(last)) = nodes.last_mut()
+    {
+        last.push_str(text);
+    } else {
+        nodes.push(node);
+    }
+}
+
+/// Converts the events up to the end of the current element.
+fn children(events: &mut Events) -> Vec<ast::Node> {
+    let mut nodes = Vec::new();
+    while let Some(event) = events.next() {
+        match event {
+            Event::Start(tag) => {
+                for node in element(tag, events) {
+                    push(&mut nodes, node);
+                }
+            }
+            Event::End(_) => break,
+            Event::Text(text) => push(&mut nodes, ast::Node::Text(text.as_ref().into())),
+            Event::Code(code) => nodes.push(ast::Node::InlineCode(code.as_ref().into())),
+            Event::Html(html) => nodes.push(ast::Node::HtmlBlock(html.as_ref().into())),
+            Event::InlineHtml(html) => push(&mut nodes, ast::Node::Text(html.as_ref().into())),
+            Event::SoftBreak => push(&mut nodes, ast::Node::Text(" ".into())),
+            Event::HardBreak => nodes.push(ast::Node::HardBreak),
+            Event::Rule => nodes.push(ast::Node::ThematicBreak),
+            _ => {}
+        }
+    }
+    nodes
+}
+
+/// Returns the concatenated text of the events up to the end of the current
+/// element.
+fn text(events: &mut Events) -> String {
+    let mut result = String::new();
+    for event in events.by_ref() {
+        match event {
+            Event::Text(text) | Event::Html(text) => result.push_str(&text),
+            Event::End(_) => break,
+            _ => {}
+        }
+    }
+    result
+}
+
+/// Converts a list item, whose paragraphs are joined by line breaks since TyX
+/// list items only hold inline nodes and nested lists.
+fn list_item(number: Option<u32>, events: &mut Events) -> ast::ListItem {
+    let checked = match events.peek() {
+        Some(Event::TaskListMarker(checked)) => Some(*checked),
+        _ => None,
+    };
+    if checked.is_some() {
+        events.next();
+    }
+
+    let mut content = Vec::new();
+    for node in children(events) {
+        match node {
+            ast::Node::Paragraph(nodes) => {
+                if !content.is_empty() {
+                    content.push(ast::Node::HardBreak);
+                }
+                content.extend(nodes);
+            }
+            node => content.push(node),
+        }
+    }
+
+    match (checked, number) {
+        (Some(checked), _) => ast::ListItem::Task {
+            status: if checked {
+                ast::TaskListStatus::Checked
+            } else {
+                ast::TaskListStatus::Unchecked
+            },
+            content,
+        },
+        (None, Some(number)) => ast::ListItem::Ordered {
+            number: Some(number),
+            content,
+        },
+        (None, None) => ast::ListItem::Unordered { content },
+    }
+}
+
+/// Converts the cells of a table row.
+fn table_row(events: &mut Events) -> Vec<ast::Node> {
+    let mut cells = Vec::new();
+    while let Some(event) = events.next() {
+        match event {
+            Event::Start(Tag::TableCell) => cells.push(ast::Node::Paragraph(children(events))),
+            Event::End(_) => break,
+            _ => {}
+        }
+    }
+    cells
+}
+
+/// Converts an element, from its start tag up to its end.
+fn element(tag: Tag, events: &mut Events) -> Vec<ast::Node> {
+    let node = match tag {
+        Tag::Paragraph => ast::Node::Paragraph(children(events)),
+        Tag::Heading { level, .. } => ast::Node::Heading {
+            level: level as u8,
+            content: children(events),
+            heading_type: ast::HeadingType::Atx,
+        },
+        Tag::BlockQuote(_) => ast::Node::BlockQuote(children(events)),
+        Tag::CodeBlock(kind) => {
+            let (language, block_type) = match kind {
+                CodeBlockKind::Fenced(info) => (
+                    info.split_whitespace().next().map(Into::into),
+                    ast::CodeBlockType::Fenced,
+                ),
+                CodeBlockKind::Indented => (None, ast::CodeBlockType::Indented),
+            };
+            ast::Node::CodeBlock {
+                language,
+                content: text(events).into(),
+                block_type,
+            }
+        }
+        Tag::HtmlBlock => ast::Node::HtmlBlock(text(events).into()),
+        Tag::List(start) => {
+            let mut items = Vec::new();
+            let mut number = start.map(|start| start as u32);
+            while let Some(event) = events.next() {
+                match event {
+                    Event::Start(Tag::Item) => {
+                        items.push(list_item(number, events));
+                        number = number.map(|number| number + 1);
+                    }
+                    Event::End(_) => break,
+                    _ => {}
+                }
+            }
+            match start {
+                Some(start) => ast::Node::OrderedList {
+                    start: start as u32,
+                    items,
+                },
+                None => ast::Node::UnorderedList(items),
+            }
+        }
+        Tag::Table(alignments) => {
+            let mut headers = Vec::new();
+            let mut rows = Vec::new();
+            while let Some(event) = events.next() {
+                match event {
+                    Event::Start(Tag::TableHead) => headers = table_row(events),
+                    Event::Start(Tag::TableRow) => rows.push(table_row(events)),
+                    Event::End(_) => break,
+                    _ => {}
+                }
+            }
+            ast::Node::Table {
+                headers,
+                alignments: alignments
+                    .into_iter()
+                    .map(|alignment| match alignment {
+                        Alignment::None => ast::TableAlignment::None,
+                        Alignment::Left => ast::TableAlignment::Left,
+                        Alignment::Center => ast::TableAlignment::Center,
+                        Alignment::Right => ast::TableAlignment::Right,
+                    })
+                    .collect(),
+                rows,
+            }
+        }
+        Tag::Emphasis => ast::Node::Emphasis(children(events)),
+        Tag::Strong => ast::Node::Strong(children(events)),
+        Tag::Strikethrough => ast::Node::Strikethrough(children(events)),
+        Tag::Link {
+            dest_url, title, ..
+        } => ast::Node::Link {
+            url: dest_url.as_ref().into(),
+            title: (!title.is_empty()).then(|| title.as_ref().into()),
+            content: children(events),
+        },
+        Tag::Image {
+            dest_url, title, ..
+        } => ast::Node::Image {
+            url: dest_url.as_ref().into(),
+            title: (!title.is_empty()).then(|| title.as_ref().into()),
+            alt: children(events),
+        },
+        // Other elements, e.g. footnote definitions, are flattened.
+        _ => return children(events),
+    };
+    vec![node]
+}
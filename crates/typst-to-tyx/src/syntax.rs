@@ -0,0 +1,1148 @@
+//! Converts Typst source code to TyX by walking its syntax tree.
+//!
+//! Unlike the `typlite` based [`typst_to_tyx`](crate::typst_to_tyx), this keeps
+//! math, alignment, text formats, function calls and set rules. Anything that
+//! isn't recognized becomes a [`TyXNode::Typstcode`] node, so that nothing is
+//! silently dropped.
+//!
+//! Files generated by TyX are restored losslessly: their settings are read from
+//! the `<tyx-settings>` metadata instead of their set rules, and the code
+//! between the `// Preamble` and `// Content` comments becomes the preamble.
+use typst::syntax::{
+    SyntaxKind, SyntaxNode,
+    ast::{self, AstNode},
+};
+use tyx_schema::{self as s, TextFormat, TyXNode};
+
+use crate::plain;
+
+/// The units TyX lengths support.
+const LENGTH_UNITS: [&str; 7] = ["pt", "mm", "cm", "in", "em", "fr", "%"];
+
+/// The functions TyX can edit without them being defined in the settings.
+const BUILTIN_FUNCTIONS: [&str; 3] = ["h", "v", "footnote"];
+
+/// Converts Typst source code to a [`TyXDocument`](s::TyXDocument).
+pub fn typst_source_to_tyx(text: &str) -> s::TyXDocument {
+    let root = typst::syntax::parse(text);
+    let mut converter = Converter::default();
+    let mut blocks = Blocks::default();
+    let mut direction = None;
+    let mut preamble = None;
+
+    match generated(text, &root) {
+        Some(generated) => {
+            converter.settings = generated.settings;
+            preamble = generated.preamble;
+            // The content's set rules come from Typst code nodes, not settings.
+            converter.depth = 1;
+            let content = || root.children().skip(generated.content);
+            match directed(content()) {
+                Some((value, body)) => {
+                    direction = Some(s::TyXDirection(Some(value)));
+                    converter.children(
+                        body.to_untyped().children(),
+                        &Style::default(),
+                        &mut blocks,
+                    );
+                }
+                None => converter.children(content(), &Style::default(), &mut blocks),
+            }
+        }
+        None => converter.children(root.children(), &Style::default(), &mut blocks),
+    }
+
+    let mut children = blocks.finish();
+    if children.is_empty() {
+        children.push(paragraph(Vec::new()));
+    }
+
+    s::TyXDocument {
+        schema: Some("https://tyx-editor.com/schemas/tyx-document.schema.json".into()),
+        version: tyx_version::VERSION.into(),
+        preamble,
+        content: Some(s::TyXDocumentContent {
+            root: TyXNode::Root {
+                children,
+                direction,
+            },
+        }),
+        settings: Some(converter.settings),
+        filename: None,
+        dirty: Some(false),
+        id: None,
+    }
+}
+
+/// Returns the settings and preamble TyX embeds in the Typst files it
+/// generates, or `None` if the given source code wasn't generated by TyX.
+pub(crate) fn embedded_settings(text: &str) -> Option<(s::TyXDocumentSettings, Option<String>)> {
+    let root = typst::syntax::parse(text);
+    generated(text, &root).map(|generated| (generated.settings, generated.preamble))
+}
+
+/// The sections of a Typst file generated by TyX.
+struct Generated {
+    /// The settings embedded as `<tyx-settings>` metadata.
+    settings: s::TyXDocumentSettings,
+    /// The code of the preamble section, if there is one.
+    preamble: Option<String>,
+    /// The index of the first top-level node of the content section.
+    content: usize,
+}
+
+/// Splits a Typst file generated by TyX into its sections, returning `None` if
+/// it has no embedded settings.
+fn generated(text: &str, root: &SyntaxNode) -> Option<Generated> {
+    let mut settings = None;
+    let mut preamble_start = None;
+    let mut content = None;
+    let mut previous = None;
+    let mut offset = 0;
+
+    for (i, child) in root.children().enumerate() {
+        match child.kind() {
+            SyntaxKind::LineComment if content.is_none() => match child.text().as_str() {
+                "// Preamble" if preamble_start.is_none() => {
+                    preamble_start = Some(offset + child.len() + 1)
+                }
+                "// Content" => content = Some((i + 1, offset)),
+                _ => {}
+            },
+            SyntaxKind::Label
+                if settings.is_none() && child.text().as_str() == "<tyx-settings>" =>
+            {
+                settings = previous.and_then(metadata_settings)
+            }
+            SyntaxKind::Space | SyntaxKind::Hash => {}
+            _ => previous = Some(child),
+        }
+        offset += child.len();
+    }
+
+    let (content, content_start) = content.unwrap_or((usize::MAX, text.len()));
+    let preamble = preamble_start.and_then(|start| {
+        let preamble = text.get(start..content_start)?;
+        Some(
+            preamble
+                .strip_suffix("\n\n")
+                .unwrap_or(preamble)
+                .to_string(),
+        )
+    });
+    Some(Generated {
+        settings: settings?,
+        preamble,
+        content,
+    })
+}
+
+/// Parses the settings JSON in the raw block of a `metadata` call.
+fn metadata_settings(node: &SyntaxNode) -> Option<s::TyXDocumentSettings> {
+    /// Finds the first raw block in the node.
+    fn find_raw(node: &SyntaxNode) -> Option<ast::Raw<'_>> {
+        node.cast().or_else(|| node.children().find_map(find_raw))
+    }
+
+    let call = node.cast::<ast::FuncCall>()?;
+    if ident(call.callee())? != "metadata" {
+        return None;
+    }
+    let json = find_raw(node)?
+        .lines()
+        .map(|line| line.get().to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+    serde_json::from_str(&json).ok()
+}
+
+/// The text format and inline style applied to converted text.
+#[derive(Clone, Default)]
+struct Style {
+    format: TextFormat,
+    style: Option<String>,
+}
+
+impl Style {
+    /// Returns the style with the given text format added.
+    fn with_format(&self, format: TextFormat) -> Style {
+        Style {
+            format: self.format | format,
+            style: self.style.clone(),
+        }
+    }
+
+    /// Returns the style with its inline style changed by the given function.
+    fn with_text_style(&self, change: impl FnOnce(&mut s::TextStyle)) -> Style {
+        let mut text_style = self
+            .style
+            .as_deref()
+            .map(|style| s::TextStyle::parse(style).0)
+            .unwrap_or_default();
+        change(&mut text_style);
+        Style {
+            format: self.format,
+            style: Some(text_style.to_string()),
+        }
+    }
+}
+
+/// Creates a text node with the given style.
+fn styled(text: String, style: &Style) -> TyXNode {
+    TyXNode::Text {
+        format: style.format,
+        text,
+        style: style.style.clone(),
+    }
+}
+
+/// Creates a paragraph node.
+fn paragraph(children: Vec<TyXNode>) -> TyXNode {
+    TyXNode::Paragraph {
+        children,
+        direction: None,
+        format: s::TyXNodeFormat::X,
+    }
+}
+
+/// Converts text to text nodes separated by line breaks.
+fn lines(text: &str) -> Vec<TyXNode> {
+    let mut children = Vec::new();
+    for (i, line) in text.split('\n').enumerate() {
+        if i != 0 {
+            children.push(TyXNode::Linebreak);
+        }
+        if !line.is_empty() {
+            children.push(plain(line.into()));
+        }
+    }
+    children
+}
+
+/// Creates a Typst code node holding the given code.
+fn typst_code(code: &str) -> TyXNode {
+    TyXNode::Typstcode {
+        text: s::TyXNodeText {
+            editor_state: s::TyXNodeTextEditorState {
+                root: TyXNode::Root {
+                    children: vec![paragraph(lines(code))],
+                    direction: None,
+                }
+                .into(),
+            },
+        },
+    }
+}
+
+/// Returns the source code of an expression in markup.
+fn code(expr: ast::Expr<'_>) -> String {
+    let code = expr.to_untyped().clone().into_text();
+    if expr.hash() {
+        format!("#{code}")
+    } else {
+        code.into()
+    }
+}
+
+/// Checks whether the node is text made of whitespace only.
+fn is_blank(node: &TyXNode) -> bool {
+    matches!(node, TyXNode::Text { text, .. } if text.trim().is_empty())
+}
+
+/// Trims the whitespace around the children of a paragraph and after its
+/// line breaks.
+fn trim(nodes: Vec<TyXNode>) -> Vec<TyXNode> {
+    let mut result: Vec<TyXNode> = Vec::new();
+    for mut node in nodes {
+        if let TyXNode::Text { text, .. } = &mut node
+            && matches!(result.last(), None | Some(TyXNode::Linebreak))
+        {
+            *text = text.trim_start().into();
+            if text.is_empty() {
+                continue;
+            }
+        }
+        result.push(node);
+    }
+    while let Some(TyXNode::Text { text, .. }) = result.last_mut() {
+        *text = text.trim_end().into();
+        if !text.is_empty() {
+            break;
+        }
+        result.pop();
+    }
+    result
+}
+
+/// Joins the children of paragraphs with line breaks, keeping other blocks.
+fn inline(blocks: Vec<TyXNode>) -> Vec<TyXNode> {
+    let mut children = Vec::new();
+    for block in blocks {
+        if !children.is_empty() {
+            children.push(TyXNode::Linebreak);
+        }
+        match block {
+            TyXNode::Paragraph {
+                children: inline, ..
+            } => children.extend(inline),
+            block => children.push(block),
+        }
+    }
+    children
+}
+
+/// Splits the blocks of a list item's body into list items, where nested lists
+/// get their own items, as in Lexical.
+fn list_items(blocks: Vec<TyXNode>) -> Vec<TyXNode> {
+    let mut items = Vec::new();
+    let mut children = Vec::new();
+    for block in blocks {
+        if let TyXNode::List { .. } = block {
+            if !children.is_empty() {
+                items.push(std::mem::take(&mut children));
+            }
+            items.push(vec![block]);
+        } else {
+            children.push(block);
+        }
+    }
+    if !children.is_empty() || items.is_empty() {
+        items.push(children);
+    }
+
+    items
+        .into_iter()
+        .map(|children| TyXNode::Listitem {
+            checked: None,
+            children: inline(children),
+            value: 0,
+        })
+        .collect()
+}
+
+/// Numbers list items as Lexical does, where an item holding a nested list
+/// shares its value with the next item.
+fn number_items(items: &mut [TyXNode], start: i64) {
+    let mut value = start;
+    for item in items {
+        if let TyXNode::Listitem {
+            children,
+            value: item_value,
+            ..
+        } = item
+        {
+            *item_value = value;
+            if !matches!(children.as_slice(), [TyXNode::List { .. }]) {
+                value += 1;
+            }
+        }
+    }
+}
+
+/// Applies a direction to a block node, unless it already has one.
+fn with_direction(mut node: TyXNode, value: s::TyXDirectionValue) -> TyXNode {
+    if let TyXNode::Root { direction, .. }
+    | TyXNode::Paragraph { direction, .. }
+    | TyXNode::List { direction, .. }
+    | TyXNode::Quote { direction, .. }
+    | TyXNode::Table { direction, .. }
+    | TyXNode::Tablecell { direction, .. } = &mut node
+        && direction.is_none()
+    {
+        *direction = Some(s::TyXDirection(Some(value)));
+    }
+    node
+}
+
+/// Applies an alignment format to a paragraph, unless it already has one.
+fn with_format(mut node: TyXNode, value: s::TyXNodeFormat) -> TyXNode {
+    if let TyXNode::Paragraph { format, .. } = &mut node
+        && *format == s::TyXNodeFormat::X
+    {
+        *format = value;
+    }
+    node
+}
+
+/// Returns the heading tag of the given depth, if TyX supports it.
+fn heading_tag(depth: i64) -> Option<s::TyXNodeTag> {
+    Some(match depth {
+        1 => s::TyXNodeTag::H1,
+        2 => s::TyXNodeTag::H2,
+        3 => s::TyXNodeTag::H3,
+        4 => s::TyXNodeTag::H4,
+        5 => s::TyXNodeTag::H5,
+        6 => s::TyXNodeTag::H6,
+        _ => return None,
+    })
+}
+
+/// Returns the value of a direction.
+fn direction(expr: ast::Expr<'_>) -> Option<s::TyXDirectionValue> {
+    match ident(expr)? {
+        "ltr" => Some(s::TyXDirectionValue::Ltr),
+        "rtl" => Some(s::TyXDirectionValue::Rtl),
+        _ => None,
+    }
+}
+
+/// Returns the direction and body of markup made of a single
+/// `text(dir: ..)[..]` call, as TyX generates for nodes with a direction.
+fn directed<'a>(
+    children: impl Iterator<Item = &'a SyntaxNode>,
+) -> Option<(s::TyXDirectionValue, ast::Markup<'a>)> {
+    let mut exprs =
+        children.filter(|child| !matches!(child.kind(), SyntaxKind::Space | SyntaxKind::Hash));
+    let (Some(call), None) = (exprs.next(), exprs.next()) else {
+        return None;
+    };
+    let call = call.cast::<ast::FuncCall>()?;
+    let args = Arguments::new(call.args())?;
+    let (Some("text"), [("dir", value)]) = (ident(call.callee()), args.named.as_slice()) else {
+        return None;
+    };
+    Some((direction(*value)?, content(args.single()?)?))
+}
+
+/// Returns the body of a content block.
+fn content(expr: ast::Expr<'_>) -> Option<ast::Markup<'_>> {
+    match expr {
+        ast::Expr::Content(content) => Some(content.body()),
+        _ => None,
+    }
+}
+
+/// Returns the value of a string literal.
+fn string(expr: ast::Expr<'_>) -> Option<String> {
+    match expr {
+        ast::Expr::Str(string) => Some(string.get().into()),
+        _ => None,
+    }
+}
+
+/// Returns the value of a boolean literal.
+fn boolean(expr: ast::Expr<'_>) -> Option<bool> {
+    match expr {
+        ast::Expr::Bool(boolean) => Some(boolean.get()),
+        _ => None,
+    }
+}
+
+/// Returns the value of an integer literal.
+fn integer(expr: ast::Expr<'_>) -> Option<i64> {
+    match expr {
+        ast::Expr::Int(integer) => Some(integer.get()),
+        _ => None,
+    }
+}
+
+/// Returns the name of an identifier.
+fn ident(expr: ast::Expr<'_>) -> Option<&str> {
+    match expr {
+        ast::Expr::Ident(ident) => Some(ident.as_str()),
+        _ => None,
+    }
+}
+
+/// Returns the value and unit of a length literal, e.g. `1.5` and `em` for
+/// `1.5em`, as written in the source.
+fn length(expr: ast::Expr<'_>) -> Option<s::TyXLength> {
+    let ast::Expr::Numeric(numeric) = expr else {
+        return None;
+    };
+    let text = numeric.to_untyped().text();
+    let unit = LENGTH_UNITS.into_iter().find(|unit| text.ends_with(unit))?;
+    Some(s::TyXLength {
+        unit: Some(unit.into()),
+        value: Some(text[..text.len() - unit.len()].into()),
+    })
+}
+
+/// Returns the color of a call to `rgb` with a hexadecimal string, as
+/// exported for the colors of text.
+fn color(expr: ast::Expr<'_>) -> Option<s::Color> {
+    let ast::Expr::FuncCall(call) = expr else {
+        return None;
+    };
+    let args = Arguments::new(call.args())?;
+    if ident(call.callee())? != "rgb" || !args.allows(&[]) {
+        return None;
+    }
+    let hex = string(args.single()?)?;
+    if !hex.starts_with('#') {
+        return None;
+    }
+    s::Color::parse(&hex)
+}
+
+/// Returns the font families of a string or an array of strings.
+fn font_families(expr: ast::Expr<'_>) -> Option<Vec<String>> {
+    match expr {
+        ast::Expr::Str(family) => Some(vec![family.get().into()]),
+        ast::Expr::Array(array) => {
+            let families = array
+                .items()
+                .map(|item| match item {
+                    ast::ArrayItem::Pos(expr) => string(expr),
+                    ast::ArrayItem::Spread(_) => None,
+                })
+                .collect::<Option<Vec<_>>>()?;
+            (!families.is_empty()).then_some(families)
+        }
+        _ => None,
+    }
+}
+
+/// Returns the font size of a length in `pt` or `em`.
+fn font_size(expr: ast::Expr<'_>) -> Option<s::FontSize> {
+    let length = length(expr)?;
+    let unit = match length.unit.as_deref()? {
+        "pt" => s::FontSizeUnit::Pt,
+        "em" => s::FontSizeUnit::Em,
+        _ => return None,
+    };
+    let value = length.value?.parse().ok()?;
+    Some(s::FontSize { value, unit })
+}
+
+/// Collects the content blocks of a sum of content blocks, e.g. `[a] + [b]`.
+fn summands<'a>(expr: ast::Expr<'a>, bodies: &mut Vec<ast::Markup<'a>>) -> Option<()> {
+    match expr {
+        ast::Expr::Content(content) => bodies.push(content.body()),
+        ast::Expr::Binary(binary) if matches!(binary.op(), ast::BinOp::Add) => {
+            summands(binary.lhs(), bodies)?;
+            summands(binary.rhs(), bodies)?;
+        }
+        _ => return None,
+    }
+    Some(())
+}
+
+/// The arguments of a function call or set rule.
+struct Arguments<'a> {
+    positional: Vec<ast::Expr<'a>>,
+    named: Vec<(&'a str, ast::Expr<'a>)>,
+}
+
+impl<'a> Arguments<'a> {
+    /// Collects the arguments, returning `None` if some argument is spread.
+    fn new(args: ast::Args<'a>) -> Option<Self> {
+        let mut positional = Vec::new();
+        let mut named = Vec::new();
+        for arg in args.items() {
+            match arg {
+                ast::Arg::Pos(expr) => positional.push(expr),
+                ast::Arg::Named(arg) => named.push((arg.name().as_str(), arg.expr())),
+                ast::Arg::Spread(_) => return None,
+            }
+        }
+        Some(Self { positional, named })
+    }
+
+    /// Returns the named argument with the given name.
+    fn named(&self, name: &str) -> Option<ast::Expr<'a>> {
+        self.named
+            .iter()
+            .find(|(arg_name, _)| *arg_name == name)
+            .map(|(_, expr)| *expr)
+    }
+
+    /// Checks that there are no named arguments other than the given ones.
+    fn allows(&self, names: &[&str]) -> bool {
+        self.named.iter().all(|(name, _)| names.contains(name))
+    }
+
+    /// Returns the positional argument, if there is exactly one.
+    fn single(&self) -> Option<ast::Expr<'a>> {
+        match self.positional.as_slice() {
+            [expr] => Some(*expr),
+            _ => None,
+        }
+    }
+}
+
+/// Collects converted block nodes, gathering inline nodes into paragraphs.
+#[derive(Default)]
+struct Blocks {
+    blocks: Vec<TyXNode>,
+    inline: Vec<TyXNode>,
+    /// Whether markup list items can still be added to the last block.
+    list_open: bool,
+}
+
+impl Blocks {
+    /// Adds an inline node to the current paragraph, merging adjacent texts
+    /// with the same style.
+    fn push_inline(&mut self, node: TyXNode) {
+        if !is_blank(&node) {
+            self.list_open = false;
+        }
+        if let TyXNode::Text {
+            format,
+            style,
+            text,
+        } = &node
+            && let Some(TyXNode::Text {
+                format: last_format,
+                style: last_style,
+                text: last_text,
+            }) = self.inline.last_mut()
+            && format == last_format
+            && style == last_style
+        {
+            last_text.push_str(text);
+            return;
+        }
+        self.inline.push(node);
+    }
+
+    /// Ends the current paragraph and adds a block node.
+    fn push_block(&mut self, node: TyXNode) {
+        self.flush();
+        self.blocks.push(node);
+        self.list_open = false;
+    }
+
+    /// Adds markup list items to the preceding list of the same type, or
+    /// starts a new list.
+    fn push_list_items(&mut self, list_type: s::TyXNodeListType, start: i64, items: Vec<TyXNode>) {
+        self.flush();
+        if self.list_open
+            && let Some(TyXNode::List {
+                children,
+                list_type: last_type,
+                start,
+                ..
+            }) = self.blocks.last_mut()
+            && *last_type == list_type
+        {
+            children.extend(items);
+            number_items(children, *start);
+        } else {
+            let mut children = items;
+            number_items(&mut children, start);
+            self.blocks.push(TyXNode::List {
+                children,
+                direction: None,
+                list_type,
+                start,
+            });
+        }
+        self.list_open = true;
+    }
+
+    /// Ends the current paragraph, dropping it if it is blank.
+    fn flush(&mut self) {
+        let children = trim(std::mem::take(&mut self.inline));
+        if !children.is_empty() {
+            self.blocks.push(paragraph(children));
+            self.list_open = false;
+        }
+    }
+
+    /// Returns the collected blocks.
+    fn finish(mut self) -> Vec<TyXNode> {
+        self.flush();
+        self.blocks
+    }
+}
+
+/// Converts a Typst syntax tree to TyX nodes.
+#[derive(Default)]
+struct Converter {
+    /// The settings, restored from the embedded metadata or collected from
+    /// top-level set rules.
+    settings: s::TyXDocumentSettings,
+    /// How deeply nested the converted markup is, set rules only become
+    /// settings at the top level.
+    depth: usize,
+}
+
+impl Converter {
+    /// Converts the children of a markup node.
+    fn children<'a>(
+        &mut self,
+        children: impl Iterator<Item = &'a SyntaxNode>,
+        style: &Style,
+        out: &mut Blocks,
+    ) {
+        let mut was_stmt = false;
+        for child in children {
+            // Like `Markup::exprs`, ignores the newline right after a statement.
+            let after_stmt = std::mem::replace(&mut was_stmt, child.kind().is_stmt());
+            if child.kind() == SyntaxKind::Space {
+                if !after_stmt {
+                    out.push_inline(styled(" ".into(), style));
+                }
+            } else if let Some(expr) = child.cast::<ast::Expr>() {
+                self.expr(expr, style, out);
+            } else if child.kind() == SyntaxKind::LineComment {
+                // Keeps the line break ending the comment, so that the code
+                // after it isn't commented out when exporting
+                out.push_inline(typst_code(&format!("{}\n", child.text())));
+            } else if !matches!(child.kind(), SyntaxKind::Hash | SyntaxKind::Semicolon) {
+                // Block comments, errors and anything else unrecognized
+                out.push_inline(typst_code(&child.clone().into_text()));
+            }
+        }
+    }
+
+    /// Converts nested markup, e.g. the body of a strong emphasis.
+    fn scoped(&mut self, markup: ast::Markup<'_>, style: &Style, out: &mut Blocks) {
+        self.depth += 1;
+        self.children(markup.to_untyped().children(), style, out);
+        self.depth -= 1;
+    }
+
+    /// Converts nested markup to blocks.
+    fn nested(&mut self, markup: ast::Markup<'_>, style: &Style) -> Vec<TyXNode> {
+        let mut blocks = Blocks::default();
+        self.scoped(markup, style, &mut blocks);
+        blocks.finish()
+    }
+
+    /// Converts an expression in markup.
+    fn expr(&mut self, expr: ast::Expr<'_>, style: &Style, out: &mut Blocks) {
+        if expr.to_untyped().erroneous() {
+            out.push_inline(typst_code(&code(expr)));
+            return;
+        }
+
+        match expr {
+            ast::Expr::Text(text) => out.push_inline(styled(text.get().to_string(), style)),
+            ast::Expr::Space(_) => out.push_inline(styled(" ".into(), style)),
+            ast::Expr::Linebreak(_) => out.push_inline(TyXNode::Linebreak),
+            ast::Expr::Parbreak(_) => out.flush(),
+            ast::Expr::Escape(escape) => out.push_inline(styled(escape.get().into(), style)),
+            ast::Expr::Shorthand(shorthand) => {
+                out.push_inline(styled(shorthand.get().into(), style))
+            }
+            ast::Expr::SmartQuote(quote) => {
+                let quote = if quote.double() { "\"" } else { "'" };
+                out.push_inline(styled(quote.into(), style));
+            }
+            ast::Expr::Strong(strong) => {
+                self.scoped(strong.body(), &style.with_format(TextFormat::BOLD), out)
+            }
+            ast::Expr::Emph(emph) => {
+                self.scoped(emph.body(), &style.with_format(TextFormat::ITALIC), out)
+            }
+            ast::Expr::Raw(raw) => {
+                let text = raw
+                    .lines()
+                    .map(|line| line.get().to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                let language = raw.lang().map(|lang| lang.get().to_string());
+                self.raw(text, language, raw.block(), style, out);
+            }
+            ast::Expr::Link(link) => out.push_inline(TyXNode::Link {
+                children: vec![styled(link.get().to_string(), style)],
+                url: link.get().to_string(),
+            }),
+            ast::Expr::Heading(heading) => {
+                let children = inline(self.nested(heading.body(), style));
+                match heading_tag(heading.depth().get() as i64) {
+                    Some(tag) => out.push_block(TyXNode::Heading { children, tag }),
+                    None => out.push_inline(typst_code(&code(expr))),
+                }
+            }
+            ast::Expr::List(item) => {
+                let items = list_items(self.nested(item.body(), style));
+                out.push_list_items(s::TyXNodeListType::Bullet, 1, items);
+            }
+            ast::Expr::Enum(item) => {
+                let start = item.number().map_or(1, |number| number as i64);
+                let items = list_items(self.nested(item.body(), style));
+                out.push_list_items(s::TyXNodeListType::Number, start, items);
+            }
+            ast::Expr::Equation(equation) => out.push_inline(TyXNode::Math {
+                formula: None,
+                inline: Some(!equation.block()),
+                typst: Some(
+                    equation
+                        .body()
+                        .to_untyped()
+                        .clone()
+                        .into_text()
+                        .trim()
+                        .into(),
+                ),
+            }),
+            ast::Expr::FuncCall(call) if self.call(call, style, out).is_some() => {}
+            ast::Expr::Set(rule) if self.depth == 0 && self.set_rule(rule).is_some() => {}
+            expr => out.push_inline(typst_code(&code(expr))),
+        }
+    }
+
+    /// Converts raw text to a code block, or to inline code.
+    fn raw(
+        &mut self,
+        text: String,
+        language: Option<String>,
+        block: bool,
+        style: &Style,
+        out: &mut Blocks,
+    ) {
+        if block {
+            out.push_block(TyXNode::Code {
+                children: lines(&text),
+                language,
+            });
+        } else {
+            out.push_inline(styled(text, &style.with_format(TextFormat::CODE)));
+        }
+    }
+
+    /// Converts a function call, returning `None` if it isn't recognized.
+    fn call(&mut self, call: ast::FuncCall<'_>, style: &Style, out: &mut Blocks) -> Option<()> {
+        let name = ident(call.callee())?;
+        let args = Arguments::new(call.args())?;
+        match name {
+            "strong" => self.text_format(&args, TextFormat::BOLD, style, out),
+            "emph" => self.text_format(&args, TextFormat::ITALIC, style, out),
+            "underline" => self.text_format(&args, TextFormat::UNDERLINE, style, out),
+            "strike" => self.text_format(&args, TextFormat::STRIKETHROUGH, style, out),
+            "sub" => self.text_format(&args, TextFormat::SUBSCRIPT, style, out),
+            "super" => self.text_format(&args, TextFormat::SUPERSCRIPT, style, out),
+            "highlight" => self.highlight(&args, style, out),
+            "lower" => self.text_format(&args, TextFormat::LOWERCASE, style, out),
+            "upper" => self.text_format(&args, TextFormat::UPPERCASE, style, out),
+            "text" => self.text(&args, style, out),
+            "raw" => {
+                if !args.allows(&["block", "lang"]) {
+                    return None;
+                }
+                let text = string(args.single()?)?;
+                let block = args.named("block").map_or(Some(false), boolean)?;
+                let language = args.named("lang").map(string).unwrap_or_default();
+                self.raw(text, language, block, style, out);
+                Some(())
+            }
+            "align" => {
+                let ([alignment, body], true) = (args.positional.as_slice(), args.allows(&[]))
+                else {
+                    return None;
+                };
+                let format = match ident(*alignment)? {
+                    "left" => s::TyXNodeFormat::Left,
+                    "start" => s::TyXNodeFormat::Start,
+                    "center" => s::TyXNodeFormat::Center,
+                    "right" => s::TyXNodeFormat::Right,
+                    "end" => s::TyXNodeFormat::End,
+                    _ => return None,
+                };
+                self.paragraph_format(*body, format, style, out)
+            }
+            "par" => {
+                if args.allows(&["justify"]) && boolean(args.named("justify")?)? {
+                    self.paragraph_format(args.single()?, s::TyXNodeFormat::Justify, style, out)
+                } else {
+                    None
+                }
+            }
+            "quote" => {
+                if !args.allows(&["block"]) || !boolean(args.named("block")?)? {
+                    return None;
+                }
+                let body = content(args.single()?)?;
+                let children = inline(self.nested(body, style));
+                out.push_block(TyXNode::Quote {
+                    children,
+                    direction: None,
+                });
+                Some(())
+            }
+            "heading" => {
+                if !args.allows(&["depth"]) {
+                    return None;
+                }
+                let depth = args.named("depth").map_or(Some(1), integer)?;
+                let body = content(args.single()?)?;
+                let tag = heading_tag(depth)?;
+                let children = inline(self.nested(body, style));
+                out.push_block(TyXNode::Heading { children, tag });
+                Some(())
+            }
+            "link" => {
+                if !args.allows(&[]) {
+                    return None;
+                }
+                let (url, children) = match args.positional.as_slice() {
+                    [url] => {
+                        let url = string(*url)?;
+                        (url.clone(), vec![styled(url, style)])
+                    }
+                    [url, body] => {
+                        let url = string(*url)?;
+                        let body = content(*body)?;
+                        (url, inline(self.nested(body, style)))
+                    }
+                    _ => return None,
+                };
+                out.push_inline(TyXNode::Link { children, url });
+                Some(())
+            }
+            "image" => {
+                if !args.allows(&[]) {
+                    return None;
+                }
+                let src = string(args.single()?)?;
+                out.push_inline(TyXNode::Image { src });
+                Some(())
+            }
+            "line" => {
+                if !args.allows(&["length"])
+                    || !args.positional.is_empty()
+                    || args.named("length")?.to_untyped().clone().into_text() != "100%"
+                {
+                    return None;
+                }
+                out.push_block(TyXNode::Horizontalrule);
+                Some(())
+            }
+            "list" => self.list(&args, s::TyXNodeListType::Bullet, style, out),
+            "enum" => self.list(&args, s::TyXNodeListType::Number, style, out),
+            "table" => self.table(&args, style, out),
+            name if BUILTIN_FUNCTIONS.contains(&name)
+                || self.settings.functions.contains_key(name) =>
+            {
+                let node = self.function(name, &args)?;
+                out.push_inline(node);
+                Some(())
+            }
+            _ => None,
+        }
+    }
+
+    /// Converts a call to a text format function, e.g. `strong[...]`.
+    fn text_format(
+        &mut self,
+        args: &Arguments<'_>,
+        format: TextFormat,
+        style: &Style,
+        out: &mut Blocks,
+    ) -> Option<()> {
+        if !args.allows(&[]) {
+            return None;
+        }
+        let body = content(args.single()?)?;
+        self.scoped(body, &style.with_format(format), out);
+        Some(())
+    }
+
+    /// Converts a call to `highlight`, either the highlight text format or,
+    /// with a `fill`, the background color of text.
+    fn highlight(&mut self, args: &Arguments<'_>, style: &Style, out: &mut Blocks) -> Option<()> {
+        let [("fill", fill)] = args.named.as_slice() else {
+            return self.text_format(args, TextFormat::HIGHLIGHT, style, out);
+        };
+        let color = color(*fill)?;
+        let body = content(args.single()?)?;
+        let style = style.with_text_style(|text_style| text_style.background_color = Some(color));
+        self.scoped(body, &style, out);
+        Some(())
+    }
+
+    /// Converts a call to `text` setting either the direction or the color,
+    /// fonts and size of text.
+    fn text(&mut self, args: &Arguments<'_>, style: &Style, out: &mut Blocks) -> Option<()> {
+        let body = content(args.single()?)?;
+        if let [("dir", dir)] = args.named.as_slice() {
+            let direction = direction(*dir)?;
+            for block in self.nested(body, style) {
+                out.push_block(with_direction(block, direction));
+            }
+            return Some(());
+        }
+        if args.named.is_empty() {
+            return None;
+        }
+        let mut changes = s::TextStyle::default();
+        for (name, value) in &args.named {
+            match *name {
+                "fill" => changes.color = Some(color(*value)?),
+                "font" => changes.font_families = font_families(*value)?,
+                "size" => changes.font_size = Some(font_size(*value)?),
+                _ => return None,
+            }
+        }
+        let style = style.with_text_style(|text_style| {
+            text_style.color = changes.color.or(text_style.color);
+            if !changes.font_families.is_empty() {
+                text_style.font_families = changes.font_families;
+            }
+            text_style.font_size = changes.font_size.or(text_style.font_size);
+        });
+        self.scoped(body, &style, out);
+        Some(())
+    }
+
+    /// Converts a call wrapping paragraphs with the given alignment format.
+    fn paragraph_format(
+        &mut self,
+        body: ast::Expr<'_>,
+        format: s::TyXNodeFormat,
+        style: &Style,
+        out: &mut Blocks,
+    ) -> Option<()> {
+        let body = content(body)?;
+        for block in self.nested(body, style) {
+            out.push_block(with_format(block, format));
+        }
+        Some(())
+    }
+
+    /// Converts a call to `list` or `enum`, whose items may be joined with `+`
+    /// to hold nested lists.
+    fn list(
+        &mut self,
+        args: &Arguments<'_>,
+        list_type: s::TyXNodeListType,
+        style: &Style,
+        out: &mut Blocks,
+    ) -> Option<()> {
+        let start = if list_type == s::TyXNodeListType::Number && args.allows(&["start"]) {
+            args.named("start").map_or(Some(1), integer)?
+        } else if args.allows(&[]) {
+            1
+        } else {
+            return None;
+        };
+
+        let mut bodies = Vec::new();
+        for expr in &args.positional {
+            summands(*expr, &mut bodies)?;
+        }
+        if bodies.is_empty() {
+            return None;
+        }
+
+        let mut children = Vec::new();
+        for body in bodies {
+            children.extend(list_items(self.nested(body, style)));
+        }
+        number_items(&mut children, start);
+        out.push_block(TyXNode::List {
+            children,
+            direction: None,
+            list_type,
+            start,
+        });
+        Some(())
+    }
+
+    /// Converts a call to `table`, whose cells are content blocks.
+    fn table(&mut self, args: &Arguments<'_>, style: &Style, out: &mut Blocks) -> Option<()> {
+        if !args.allows(&["columns"]) {
+            return None;
+        }
+        let columns = match args.named("columns")? {
+            ast::Expr::Int(columns) => usize::try_from(columns.get()).ok()?,
+            ast::Expr::Array(columns) => columns.items().count(),
+            ast::Expr::Parenthesized(_) => 1,
+            _ => return None,
+        };
+        let bodies = args
+            .positional
+            .iter()
+            .map(|expr| content(*expr))
+            .collect::<Option<Vec<_>>>()?;
+        if columns == 0 || bodies.is_empty() {
+            return None;
+        }
+
+        let mut children = Vec::new();
+        for row in bodies.chunks(columns) {
+            let mut cells = Vec::new();
+            for body in row {
+                let (direction, body) = match directed(body.to_untyped().children()) {
+                    Some((value, body)) => (Some(s::TyXDirection(Some(value))), body),
+                    None => (None, *body),
+                };
+                let mut blocks = self.nested(body, style);
+                if blocks.is_empty() {
+                    blocks.push(paragraph(Vec::new()));
+                }
+                cells.push(TyXNode::Tablecell {
+                    children: blocks,
+                    direction,
+                });
+            }
+            children.push(TyXNode::Tablerow { children: cells });
+        }
+        out.push_block(TyXNode::Table {
+            children,
+            direction: None,
+        });
+        Some(())
+    }
+
+    /// Converts a call to a function TyX can edit, e.g. `h(1em)`.
+    fn function(&mut self, name: &str, args: &Arguments<'_>) -> Option<TyXNode> {
+        let mut position_parameters = Vec::new();
+        for expr in &args.positional {
+            position_parameters.push(self.value(*expr)?);
+        }
+        let mut named_parameters = std::collections::HashMap::new();
+        for (name, expr) in &args.named {
+            named_parameters.insert(name.to_string(), self.value(*expr)?);
+        }
+        Some(TyXNode::Functioncall {
+            name: Some(name.into()),
+            named_parameters,
+            position_parameters,
+        })
+    }
+
+    /// Converts a function argument to a TyX value.
+    fn value(&mut self, expr: ast::Expr<'_>) -> Option<s::TyXValue> {
+        Some(match expr {
+            ast::Expr::Numeric(_) => {
+                let s::TyXLength { unit, value } = length(expr)?;
+                s::TyXValue::Length { unit, value }
+            }
+            ast::Expr::None(_) => s::TyXValue::Length {
+                unit: None,
+                value: None,
+            },
+            ast::Expr::Bool(value) => s::TyXValue::Boolean {
+                value: Some(value.get()),
+            },
+            ast::Expr::Content(content) => s::TyXValue::Content {
+                value: Some(TyXNode::Root {
+                    children: self.nested(content.body(), &Style::default()),
+                    direction: None,
+                }),
+            },
+            _ => return None,
+        })
+    }
+
+    /// Converts a top-level set rule to document settings, returning `None`
+    /// if some of its arguments aren't recognized.
+    fn set_rule(&mut self, rule: ast::SetRule<'_>) -> Option<()> {
+        let target = ident(rule.target())?;
+        let args = Arguments::new(rule.args())?;
+        if rule.condition().is_some() || !args.positional.is_empty() {
+            return None;
+        }
+
+        let mut settings = self.settings.clone();
+        for (name, value) in &args.named {
+            match (target, *name) {
+                ("document", "title") => settings.title = Some(string(*value)?),
+                ("document", "author") => settings.author = Some(string(*value)?),
+                ("page", "paper") => settings.paper = Some(string(*value)?),
+                ("page", "flipped") => settings.flipped = Some(boolean(*value)?),
+                ("page", "columns") => settings.columns = Some(integer(*value)? as f64),
+                ("text", "lang") => settings.language = Some(string(*value)?),
+                ("par", "justify") => settings.justified = Some(boolean(*value)?),
+                ("par", "first-line-indent") => settings.indentation = Some(length(*value)?),
+                _ => return None,
+            }
+        }
+        self.settings = settings;
+        Some(())
+    }
+}
@@ -1,8 +1,14 @@
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
-pub fn serialized_tyx_to_typst(document: &str) -> String {
-    tyx_to_typst::serialized_tyx_to_typst(document)
+pub fn serialized_tyx_to_typst(document: &str) -> Result<String, JsError> {
+    tyx_to_typst::try_serialized_tyx_to_typst(document)
+        .map_err(|err| JsError::new(&err.to_string()))
+}
+
+#[wasm_bindgen]
+pub fn serialized_tyx_to_html(document: &str) -> Result<String, JsError> {
+    tyx_to_html::try_serialized_tyx_to_html(document).map_err(|err| JsError::new(&err.to_string()))
 }
 
 #[wasm_bindgen]
@@ -11,11 +17,12 @@ pub fn serialized_stringify_function(
     position_parameters: &str,
     named_parameters: &str,
     include_content: bool,
-) -> String {
-    tyx_to_typst::serialized_stringify_function(
+) -> Result<String, JsError> {
+    tyx_to_typst::try_serialized_stringify_function(
         Some(name.into()),
         position_parameters,
         named_parameters,
         include_content,
     )
+    .map_err(|err| JsError::new(&err.to_string()))
 }
@@ -1,8 +1,13 @@
 //! Convertions between TyX and other formats.
-//! Currently only Typst is supported.
+//! Typst is supported in both directions, and TyX can be exported to Markdown
+//! and HTML.
 
 pub use typst_to_tyx::*;
+pub use tyx_to_html::*;
+pub use tyx_to_markdown::*;
 pub use tyx_to_typst::*;
 
+#[cfg(test)]
+mod round_trip;
 #[cfg(test)]
 mod tests;
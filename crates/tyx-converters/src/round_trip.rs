@@ -0,0 +1,675 @@
+//! Round-trip property tests, converting arbitrary TyX documents to Typst with
+//! [`tyx_to_typst`](crate::tyx_to_typst) and back with
+//! [`typst_source_to_tyx`](crate::typst_source_to_tyx).
+//!
+//! The conversions are lossy in a few documented ways, which [`normalize`]
+//! applies to both sides before comparing them:
+//! - code highlights and tabs become plain text,
+//! - adjacent texts with the same format and style are merged, whitespace is
+//!   collapsed and trimmed around text nodes, and empty texts are dropped,
+//! - the code format overrides the other text formats,
+//! - empty paragraphs are dropped, since they generate no Typst code,
+//! - list items are renumbered as Lexical does, and bullet lists start at 1,
+//! - math loses its LaTeX formula, and is a block unless it is inline,
+//! - a root without a direction holding a single block first takes the
+//!   direction of the block, which generates the same Typst code,
+//! - the direction of a root holding a single block moves to that block.
+use std::collections::HashMap;
+
+use proptest::{collection::vec, option, prelude::*, sample::select};
+use tyx_schema::*;
+
+/// The Typst code of the generated math nodes.
+const MATH: [&str; 4] = ["x^2", "a + b", "sum_(i=1)^n i", "sqrt(2) / 2"];
+
+/// The code of the generated Typst code nodes, which the importer keeps as is.
+const TYPST_CODE: [&str; 3] = ["#box[x]", "#lorem(2)", "#rotate(10deg)[a]"];
+
+/// The generated preambles.
+const PREAMBLES: [&str; 3] = ["", "#let x = 1", "#let f(x) = x\n#set text(size: 11pt)"];
+
+/// The source of the generated image, which [`write_assets`] creates.
+pub(crate) const IMAGE: &str = "image.svg";
+
+/// Writes the files the generated documents refer to into the given directory.
+pub(crate) fn write_assets(dir: &std::path::Path) -> std::io::Result<()> {
+    std::fs::write(
+        dir.join(IMAGE),
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"/>"#,
+    )
+}
+
+/// A word of text, including characters Typst has to escape.
+const WORD: &str = r#"[a-zA-Z#*_$=@<+/'"~,:`\[\]\\-]{1,8}"#;
+
+/// The generated colors of text, with and without transparency.
+const COLORS: [&str; 3] = ["#d0021b", "#4a90e2", "#ffff0080"];
+
+/// The generated font families, leaving out the generic families, which the
+/// exporter drops.
+const FONT_FAMILIES: [&str; 3] = ["Libertinus Serif", "New Computer Modern", "DejaVu"];
+
+/// An inline style, in the canonical form the importer writes.
+fn text_style() -> impl Strategy<Value = Option<String>> {
+    let color =
+        || option::of(select(COLORS.to_vec()).prop_map(|color| Color::parse(color).unwrap()));
+    (
+        color(),
+        color(),
+        vec(select(FONT_FAMILIES.to_vec()).prop_map(String::from), 0..3),
+        option::of(select(vec![
+            FontSize {
+                value: 12.0,
+                unit: FontSizeUnit::Pt,
+            },
+            FontSize {
+                value: 8.5,
+                unit: FontSizeUnit::Pt,
+            },
+            FontSize {
+                value: 1.5,
+                unit: FontSizeUnit::Em,
+            },
+        ])),
+    )
+        .prop_map(|(color, background_color, font_families, font_size)| {
+            let style = TextStyle {
+                color,
+                background_color,
+                font_families,
+                font_size,
+            };
+            (style != TextStyle::default()).then(|| style.to_string())
+        })
+}
+
+fn text() -> impl Strategy<Value = TyXNode> {
+    (
+        proptest::string::string_regex(&format!("{WORD}( {WORD}){{0,2}}")).unwrap(),
+        // Every combination of formats, though Lexical makes lowercase and
+        // uppercase exclusive. Capitalized text is exported in small capitals,
+        // which are opened as Typst code.
+        (
+            0i64..256,
+            select(vec![
+                TextFormat::empty(),
+                TextFormat::LOWERCASE,
+                TextFormat::UPPERCASE,
+            ]),
+        )
+            .prop_map(|(format, case)| TextFormat::from_bits_retain(format) | case),
+        text_style(),
+    )
+        .prop_map(|(text, format, style)| TyXNode::Text {
+            format,
+            style,
+            text,
+        })
+}
+
+fn math() -> impl Strategy<Value = TyXNode> {
+    (select(MATH.to_vec()), any::<bool>(), option::of(Just("x"))).prop_map(
+        |(typst, inline, formula)| TyXNode::Math {
+            formula: formula.map(String::from),
+            inline: Some(inline),
+            typst: Some(typst.into()),
+        },
+    )
+}
+
+fn link() -> impl Strategy<Value = TyXNode> {
+    vec(text(), 0..3).prop_map(|children| TyXNode::Link {
+        children,
+        url: "https://typst.app".into(),
+    })
+}
+
+fn typst_code() -> impl Strategy<Value = TyXNode> {
+    select(TYPST_CODE.to_vec()).prop_map(|code| TyXNode::Typstcode {
+        text: TyXNodeText {
+            editor_state: TyXNodeTextEditorState {
+                root: TyXNode::Root {
+                    children: vec![paragraph(vec![TyXNode::Text {
+                        format: TextFormat::empty(),
+                        style: None,
+                        text: code.into(),
+                    }])],
+                    direction: None,
+                }
+                .into(),
+            },
+        },
+    })
+}
+
+fn length() -> impl Strategy<Value = TyXValue> {
+    (
+        select(vec!["1", "2.5", "0"]),
+        select(vec!["pt", "mm", "cm", "in", "em", "fr", "%"]),
+    )
+        .prop_map(|(value, unit)| TyXValue::Length {
+            unit: Some(unit.into()),
+            value: Some(value.into()),
+        })
+}
+
+fn function() -> impl Strategy<Value = TyXNode> {
+    let spacing = (select(vec!["h", "v"]), length(), option::of(any::<bool>())).prop_map(
+        |(name, length, weak)| TyXNode::Functioncall {
+            name: Some(name.into()),
+            named_parameters: weak
+                .map(|weak| {
+                    HashMap::from([("weak".into(), TyXValue::Boolean { value: Some(weak) })])
+                })
+                .unwrap_or_default(),
+            position_parameters: vec![length],
+        },
+    );
+    let footnote = vec(vec(text(), 1..3).prop_map(paragraph), 1..3).prop_map(|children| {
+        TyXNode::Functioncall {
+            name: Some("footnote".into()),
+            named_parameters: HashMap::new(),
+            position_parameters: vec![TyXValue::Content {
+                value: Some(TyXNode::Root {
+                    children,
+                    direction: None,
+                }),
+            }],
+        }
+    });
+    prop_oneof![spacing, footnote]
+}
+
+fn inline() -> impl Strategy<Value = TyXNode> {
+    prop_oneof![
+        4 => text(),
+        1 => Just(TyXNode::Linebreak),
+        1 => math(),
+        1 => link(),
+        1 => Just(TyXNode::Image { src: IMAGE.into() }),
+        1 => function(),
+        1 => typst_code(),
+    ]
+}
+
+fn direction() -> impl Strategy<Value = Option<TyXDirection>> {
+    option::of(select(vec![TyXDirectionValue::Ltr, TyXDirectionValue::Rtl]))
+        .prop_map(|value| value.map(|value| TyXDirection(Some(value))))
+}
+
+fn format() -> impl Strategy<Value = TyXNodeFormat> {
+    select(vec![
+        TyXNodeFormat::X,
+        TyXNodeFormat::Left,
+        TyXNodeFormat::Start,
+        TyXNodeFormat::Center,
+        TyXNodeFormat::Right,
+        TyXNodeFormat::End,
+        TyXNodeFormat::Justify,
+    ])
+}
+
+fn paragraph(children: Vec<TyXNode>) -> TyXNode {
+    TyXNode::Paragraph {
+        children,
+        direction: None,
+        format: TyXNodeFormat::X,
+    }
+}
+
+fn formatted_paragraph(
+    children: impl Strategy<Value = Vec<TyXNode>>,
+    direction: impl Strategy<Value = Option<TyXDirection>>,
+) -> impl Strategy<Value = TyXNode> {
+    (children, format(), direction).prop_map(|(children, format, direction)| TyXNode::Paragraph {
+        children,
+        direction,
+        format,
+    })
+}
+
+fn heading() -> impl Strategy<Value = TyXNode> {
+    (
+        select(vec![
+            TyXNodeTag::H1,
+            TyXNodeTag::H2,
+            TyXNodeTag::H3,
+            TyXNodeTag::H4,
+            TyXNodeTag::H5,
+            TyXNodeTag::H6,
+        ]),
+        vec(prop_oneof![3 => text(), 1 => math(), 1 => link()], 0..3),
+    )
+        .prop_map(|(tag, children)| TyXNode::Heading { children, tag })
+}
+
+fn list_item_children() -> impl Strategy<Value = Vec<TyXNode>> {
+    vec(
+        prop_oneof![3 => text(), 1 => Just(TyXNode::Linebreak), 1 => math(), 1 => link()],
+        0..3,
+    )
+}
+
+/// Generates a list, whose items may hold nested lists if `nested` is set.
+fn list(nested: bool) -> BoxedStrategy<TyXNode> {
+    let item = if nested {
+        prop_oneof![
+            3 => list_item_children(),
+            1 => list(false).prop_map(|list| vec![list]),
+        ]
+        .boxed()
+    } else {
+        list_item_children().boxed()
+    };
+    (
+        select(vec![TyXNodeListType::Bullet, TyXNodeListType::Number]),
+        0i64..5,
+        vec(item, 1..4),
+        direction(),
+    )
+        .prop_map(|(list_type, start, items, direction)| TyXNode::List {
+            children: items
+                .into_iter()
+                .map(|children| TyXNode::Listitem {
+                    checked: None,
+                    children,
+                    value: 0,
+                })
+                .collect(),
+            direction,
+            list_type,
+            start,
+        })
+        .boxed()
+}
+
+fn code() -> impl Strategy<Value = TyXNode> {
+    (
+        option::of(select(vec!["rust", "typ"])),
+        vec(
+            prop_oneof![
+                3 => r#"[a-z(){};="\\]{1,6}"#.prop_map(|text| TyXNode::CodeHighlight { text }),
+                1 => Just(TyXNode::Linebreak),
+                1 => Just(TyXNode::Tab { text: "\t".into() }),
+            ],
+            0..6,
+        ),
+    )
+        .prop_map(|(language, children)| TyXNode::Code {
+            children,
+            language: language.map(String::from),
+        })
+}
+
+fn quote() -> impl Strategy<Value = TyXNode> {
+    (
+        vec(
+            prop_oneof![3 => text(), 1 => Just(TyXNode::Linebreak), 1 => math()],
+            0..3,
+        ),
+        direction(),
+    )
+        .prop_map(|(children, direction)| TyXNode::Quote {
+            children,
+            direction,
+        })
+}
+
+fn table() -> impl Strategy<Value = TyXNode> {
+    // The paragraphs of a cell have no direction, as it would be mistaken for
+    // the direction of the cell.
+    let cell = (
+        vec(formatted_paragraph(vec(text(), 0..3), Just(None)), 0..2),
+        direction(),
+    )
+        .prop_map(|(children, direction)| TyXNode::Tablecell {
+            children,
+            direction,
+        })
+        .boxed();
+    ((1usize..4, 1usize..3), direction())
+        .prop_flat_map(move |((columns, rows), direction)| {
+            (
+                vec(cell.clone(), columns * rows),
+                Just(columns),
+                Just(direction),
+            )
+        })
+        .prop_map(|(cells, columns, direction)| TyXNode::Table {
+            children: cells
+                .chunks(columns)
+                .map(|row| TyXNode::Tablerow {
+                    children: row.to_vec(),
+                })
+                .collect(),
+            direction,
+        })
+}
+
+fn block() -> impl Strategy<Value = TyXNode> {
+    prop_oneof![
+        4 => formatted_paragraph(vec(inline(), 0..6), direction()),
+        1 => heading(),
+        1 => list(true),
+        1 => code(),
+        1 => quote(),
+        1 => table(),
+        1 => Just(TyXNode::Horizontalrule),
+    ]
+}
+
+fn settings() -> impl Strategy<Value = TyXDocumentSettings> {
+    (
+        option::of(select(vec!["a4", "us-letter"])),
+        option::of(any::<bool>()),
+        option::of(1u8..4),
+        option::of(select(vec!["en", "he"])),
+        option::of(any::<bool>()),
+        option::of((select(vec!["1", "2.5"]), select(vec!["pt", "mm", "em"]))),
+        option::of(select(vec!["Notes", "A \"quoted\" title"])),
+        option::of(select(vec!["Ada", "Grace Hopper"])),
+    )
+        .prop_map(
+            |(paper, flipped, columns, language, justified, indentation, title, author)| {
+                TyXDocumentSettings {
+                    paper: paper.map(String::from),
+                    flipped,
+                    columns: columns.map(f64::from),
+                    language: language.map(String::from),
+                    justified,
+                    indentation: indentation.map(|(value, unit)| TyXLength {
+                        unit: Some(unit.into()),
+                        value: Some(value.into()),
+                    }),
+                    title: title.map(String::from),
+                    author: author.map(String::from),
+                    ..TyXDocumentSettings::default()
+                }
+            },
+        )
+}
+
+/// Generates arbitrary TyX documents that can be converted to Typst.
+pub(crate) fn document() -> impl Strategy<Value = TyXDocument> {
+    (
+        vec(block(), 1..6),
+        direction(),
+        settings(),
+        option::of(select(PREAMBLES.to_vec())),
+    )
+        .prop_map(|(children, direction, settings, preamble)| TyXDocument {
+            schema: None,
+            version: String::new(),
+            preamble: preamble.map(String::from),
+            content: Some(TyXDocumentContent {
+                root: TyXNode::Root {
+                    children,
+                    direction,
+                },
+            }),
+            settings: Some(settings),
+            filename: None,
+            dirty: None,
+            id: None,
+        })
+}
+
+/// Normalizes the children of a node, see the [module documentation](self).
+fn normalize_children(children: Vec<TyXNode>) -> Vec<TyXNode> {
+    let mut result: Vec<TyXNode> = Vec::new();
+    for child in children.into_iter().map(normalize) {
+        if let TyXNode::Paragraph { children, .. } = &child
+            && children.is_empty()
+        {
+            continue;
+        }
+        if let TyXNode::Text {
+            format,
+            style,
+            text,
+        } = &child
+            && let Some(TyXNode::Text {
+                format: last_format,
+                style: last_style,
+                text: last_text,
+            }) = result.last_mut()
+            && format == last_format
+            && style == last_style
+        {
+            last_text.push_str(text);
+            continue;
+        }
+        result.push(child);
+    }
+
+    result
+        .into_iter()
+        .filter_map(|child| match child {
+            TyXNode::Text {
+                format,
+                style,
+                text,
+            } => {
+                let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                (!text.is_empty()).then_some(TyXNode::Text {
+                    format,
+                    style,
+                    text,
+                })
+            }
+            child => Some(child),
+        })
+        .collect()
+}
+
+/// Normalizes a node, see the [module documentation](self).
+fn normalize(node: TyXNode) -> TyXNode {
+    match node {
+        TyXNode::Root {
+            children,
+            direction,
+        } => TyXNode::Root {
+            children: normalize_children(children),
+            direction,
+        },
+        TyXNode::Paragraph {
+            children,
+            direction,
+            format,
+        } => TyXNode::Paragraph {
+            children: normalize_children(children),
+            direction,
+            format,
+        },
+        TyXNode::Text {
+            format,
+            style,
+            text,
+        } => TyXNode::Text {
+            format: if format.contains(TextFormat::CODE) {
+                TextFormat::CODE
+            } else {
+                format
+            },
+            style,
+            text,
+        },
+        TyXNode::CodeHighlight { text } | TyXNode::Tab { text } => TyXNode::Text {
+            format: TextFormat::empty(),
+            style: None,
+            text,
+        },
+        TyXNode::Math { inline, typst, .. } => TyXNode::Math {
+            formula: None,
+            inline: Some(inline.unwrap_or(false)),
+            typst,
+        },
+        TyXNode::Listitem {
+            checked,
+            children,
+            value,
+        } => TyXNode::Listitem {
+            checked,
+            children: normalize_children(children),
+            value,
+        },
+        TyXNode::List {
+            children,
+            direction,
+            list_type,
+            start,
+        } => {
+            let start = if list_type == TyXNodeListType::Number {
+                start
+            } else {
+                1
+            };
+            let mut children = normalize_children(children);
+            let mut value = start;
+            for child in &mut children {
+                if let TyXNode::Listitem {
+                    children,
+                    value: item_value,
+                    ..
+                } = child
+                {
+                    *item_value = value;
+                    if !matches!(children.as_slice(), [TyXNode::List { .. }]) {
+                        value += 1;
+                    }
+                }
+            }
+            TyXNode::List {
+                children,
+                direction,
+                list_type,
+                start,
+            }
+        }
+        TyXNode::Code { children, language } => TyXNode::Code {
+            children: normalize_children(children),
+            language,
+        },
+        TyXNode::Quote {
+            children,
+            direction,
+        } => TyXNode::Quote {
+            children: normalize_children(children),
+            direction,
+        },
+        TyXNode::Table {
+            children,
+            direction,
+        } => TyXNode::Table {
+            children: normalize_children(children),
+            direction,
+        },
+        TyXNode::Tablerow { children } => TyXNode::Tablerow {
+            children: normalize_children(children),
+        },
+        TyXNode::Tablecell {
+            children,
+            direction,
+        } => TyXNode::Tablecell {
+            children: normalize_children(children),
+            direction,
+        },
+        TyXNode::Link { children, url } => TyXNode::Link {
+            children: normalize_children(children),
+            url,
+        },
+        TyXNode::Heading { children, tag } => TyXNode::Heading {
+            children: normalize_children(children),
+            tag,
+        },
+        TyXNode::Functioncall {
+            name,
+            named_parameters,
+            position_parameters,
+        } => TyXNode::Functioncall {
+            name,
+            named_parameters,
+            position_parameters: position_parameters
+                .into_iter()
+                .map(|value| match value {
+                    TyXValue::Content { value } => TyXValue::Content {
+                        value: value.map(normalize),
+                    },
+                    value => value,
+                })
+                .collect(),
+        },
+        node => node,
+    }
+}
+
+/// Returns the direction of a block node.
+fn block_direction(node: &mut TyXNode) -> Option<&mut Option<TyXDirection>> {
+    match node {
+        TyXNode::Paragraph { direction, .. }
+        | TyXNode::List { direction, .. }
+        | TyXNode::Quote { direction, .. }
+        | TyXNode::Table { direction, .. } => Some(direction),
+        _ => None,
+    }
+}
+
+/// Normalizes the content of a document to JSON, see the
+/// [module documentation](self).
+fn normalize_document(document: &TyXDocument) -> serde_json::Value {
+    let root = document.content.as_ref().map(|content| {
+        let mut root = content.root.clone();
+        // Taken before normalizing, since the block may be an empty paragraph
+        // which is dropped, while its direction is still exported.
+        if let TyXNode::Root {
+            children,
+            direction,
+        } = &mut root
+            && direction.is_none()
+            && let [child] = children.as_mut_slice()
+            && let Some(child_direction) = block_direction(child)
+        {
+            *direction = child_direction.take();
+        }
+        let TyXNode::Root {
+            mut children,
+            mut direction,
+        } = normalize(root)
+        else {
+            return content.root.clone();
+        };
+        if let [child] = children.as_mut_slice() {
+            if let Some(child_direction) = block_direction(child)
+                && child_direction.is_none()
+            {
+                *child_direction = direction;
+            }
+            direction = None;
+        }
+        TyXNode::Root {
+            children,
+            direction,
+        }
+    });
+    serde_json::to_value(root).unwrap()
+}
+
+proptest! {
+    #[test]
+    fn round_trip(document in document()) {
+        let typst = tyx_to_typst::tyx_to_typst(&document);
+        let restored = crate::typst_source_to_tyx(&typst);
+
+        prop_assert_eq!(&restored.preamble, &document.preamble);
+        prop_assert_eq!(
+            serde_json::to_value(&restored.settings).unwrap(),
+            serde_json::to_value(&document.settings).unwrap()
+        );
+        prop_assert_eq!(
+            normalize_document(&restored),
+            normalize_document(&document),
+            "{}",
+            typst
+        );
+    }
+}
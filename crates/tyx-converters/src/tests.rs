@@ -47,3 +47,337 @@ fn test_tyx_boolean_value_to_typst() {
         tyx_to_typst::tyx_value_to_typst(tyx_schema::TyXValue::Boolean { value: None }).is_none()
     );
 }
+
+/// Converts the given Typst source by walking its syntax tree, and returns the
+/// root's children as JSON.
+fn syntax_conv(source: &str) -> serde_json::Value {
+    let doc = crate::typst_source_to_tyx(source);
+    let root = serde_json::to_value(doc.content.expect("missing content").root).unwrap();
+    root["children"].clone()
+}
+
+#[test]
+fn test_syntax_math_and_functions() {
+    let children = syntax_conv("Let $x^2$ be #h(1em, weak: true) big.\n\n$ sum_i i $");
+    assert_eq!(
+        children,
+        serde_json::json!([
+            {"type": "paragraph", "format": "", "children": [
+                {"type": "text", "format": 0, "text": "Let "},
+                {"type": "math", "inline": true, "typst": "x^2"},
+                {"type": "text", "format": 0, "text": " be "},
+                {"type": "functioncall", "name": "h",
+                    "positionParameters": [{"type": "length", "unit": "em", "value": "1"}],
+                    "namedParameters": {"weak": {"type": "boolean", "value": true}}},
+                {"type": "text", "format": 0, "text": " big."},
+            ]},
+            {"type": "paragraph", "format": "", "children": [
+                {"type": "math", "inline": false, "typst": "sum_i i"},
+            ]},
+        ])
+    );
+}
+
+#[test]
+fn test_syntax_text_formats_and_alignment() {
+    let children = syntax_conv("*a* #underline[b] x#sub[2] #raw(\"c\")\n\n#align(center)[d]");
+    assert_eq!(
+        children,
+        serde_json::json!([
+            {"type": "paragraph", "format": "", "children": [
+                {"type": "text", "format": 1, "text": "a"},
+                {"type": "text", "format": 0, "text": " "},
+                {"type": "text", "format": 8, "text": "b"},
+                {"type": "text", "format": 0, "text": " x"},
+                {"type": "text", "format": 32, "text": "2"},
+                {"type": "text", "format": 0, "text": " "},
+                {"type": "text", "format": 16, "text": "c"},
+            ]},
+            {"type": "paragraph", "format": "center", "children": [
+                {"type": "text", "format": 0, "text": "d"},
+            ]},
+        ])
+    );
+}
+
+#[test]
+fn test_syntax_text_styles() {
+    let children = syntax_conv(
+        "#text(fill: rgb(\"#d0021b\"), font: (\"Libertinus Serif\", \"DejaVu\"))[#text(size: 1.5em)[a]] #highlight(fill: rgb(\"#ffff00\"))[b] #text(size: 2cm)[c]",
+    );
+    assert_eq!(
+        children,
+        serde_json::json!([
+            {"type": "paragraph", "format": "", "children": [
+                {"type": "text", "format": 0, "text": "a",
+                    "style": "color: #d0021b; font-family: \"Libertinus Serif\", DejaVu; font-size: 1.5em"},
+                {"type": "text", "format": 0, "text": " "},
+                {"type": "text", "format": 0, "text": "b", "style": "background-color: #ffff00"},
+                {"type": "text", "format": 0, "text": " "},
+                {"type": "typstcode", "text": {"editorState": {"root": {"type": "root", "children": [
+                    {"type": "paragraph", "format": "", "children": [
+                        {"type": "text", "format": 0, "text": "#text(size: 2cm)[c]"},
+                    ]},
+                ]}}}},
+            ]},
+        ])
+    );
+}
+
+#[test]
+fn test_syntax_comments_are_kept() {
+    let source = "a // c\nb /* d */ e";
+    let children = syntax_conv(source);
+    let code = |code: serde_json::Value| {
+        serde_json::json!({"type": "typstcode", "text": {"editorState": {"root": {"type": "root", "children": [
+            {"type": "paragraph", "format": "", "children": code},
+        ]}}}})
+    };
+    assert_eq!(
+        children,
+        serde_json::json!([
+            {"type": "paragraph", "format": "", "children": [
+                {"type": "text", "format": 0, "text": "a "},
+                code(serde_json::json!([
+                    {"type": "text", "format": 0, "text": "// c"},
+                    {"type": "linebreak"},
+                ])),
+                {"type": "text", "format": 0, "text": " b "},
+                code(serde_json::json!([{"type": "text", "format": 0, "text": "/* d */"}])),
+                {"type": "text", "format": 0, "text": " e"},
+            ]},
+        ])
+    );
+}
+
+#[test]
+fn test_syntax_nested_list() {
+    let children = syntax_conv("- a\n- b\n  - c\n- d");
+    let items = &children[0]["children"];
+    let values = items
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["value"].as_i64().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(children[0]["listType"], "bullet");
+    assert_eq!(values, [1, 2, 3, 3]);
+    assert_eq!(items[2]["children"][0]["type"], "list");
+}
+
+#[test]
+fn test_syntax_set_rules_become_settings() {
+    let doc = crate::typst_source_to_tyx(
+        "#set document(title: \"Notes\", author: \"Ada\")\n#set page(paper: \"a4\", columns: 2)\n#set text(lang: \"he\")\n#set par(first-line-indent: 1.5em)\nHi",
+    );
+    let settings = doc.settings.expect("missing settings");
+    assert_eq!(settings.title.as_deref(), Some("Notes"));
+    assert_eq!(settings.author.as_deref(), Some("Ada"));
+    assert_eq!(settings.paper.as_deref(), Some("a4"));
+    assert_eq!(settings.columns, Some(2.0));
+    assert_eq!(settings.language.as_deref(), Some("he"));
+    let indentation = settings.indentation.expect("missing indentation");
+    assert_eq!(indentation.value.as_deref(), Some("1.5"));
+    assert_eq!(indentation.unit.as_deref(), Some("em"));
+}
+
+#[test]
+fn test_syntax_unrecognized_code_is_kept() {
+    let children = syntax_conv("#let x = 1\n#set text(fill: red)");
+    let code = &children[0]["children"];
+    assert_eq!(code[0]["type"], "typstcode");
+    assert_eq!(
+        code[0]["text"]["editorState"]["root"]["children"][0]["children"][0]["text"],
+        "#let x = 1"
+    );
+    assert_eq!(
+        code[1]["text"]["editorState"]["root"]["children"][0]["children"][0]["text"],
+        "#set text(fill: red)"
+    );
+}
+
+#[test]
+fn test_syntax_restores_generated_documents() {
+    let document = r##"{"version": "", "preamble": "#let x = 1\n// not the content", "settings": {
+        "paper": "a5", "language": "he", "columns": 1.5, "justified": true,
+        "indentation": {"unit": "em", "value": "1"},
+        "functions": {"pagebreak": {"positional": [], "inline": false}}
+    }, "content": {"root": {"type": "root", "direction": "rtl", "children": [
+        {"type": "paragraph", "format": "", "children": [
+            {"type": "text", "format": 0, "text": "a"},
+            {"type": "functioncall", "name": "pagebreak"},
+            {"type": "typstcode", "text": {"editorState": {"root": {"type": "root", "children": [
+                {"type": "paragraph", "format": "", "children": [{"type": "text", "format": 0, "text": "#set page(paper: \"a4\")"}]}
+            ]}}}}
+        ]},
+        {"type": "table", "children": [{"type": "tablerow", "children": [
+            {"type": "tablecell", "direction": "ltr", "children": [
+                {"type": "paragraph", "format": "", "children": [{"type": "text", "format": 0, "text": "b"}]}
+            ]}
+        ]}]},
+        {"type": "code", "language": "rust", "children": [
+            {"type": "code-highlight", "text": "fn"},
+            {"type": "linebreak"},
+            {"type": "tab", "text": "\t"}
+        ]}
+    ]}}}"##;
+    let document: tyx_schema::TyXDocument = serde_json::from_str(document).unwrap();
+    let typst = tyx_to_typst::tyx_to_typst(&document);
+    let mut restored = crate::typst_source_to_tyx(&typst);
+
+    assert_eq!(restored.preamble, document.preamble);
+    assert_eq!(
+        serde_json::to_value(&restored.settings).unwrap(),
+        serde_json::to_value(&document.settings).unwrap()
+    );
+    let root = serde_json::to_value(&restored.content.as_ref().unwrap().root).unwrap();
+    assert_eq!(root["direction"], "rtl");
+    assert_eq!(root["children"][0]["children"][1]["type"], "functioncall");
+    assert_eq!(root["children"][0]["children"][2]["type"], "typstcode");
+    assert_eq!(
+        root["children"][1]["children"][0]["children"][0]["direction"],
+        "ltr"
+    );
+    restored.version.clear();
+    assert_eq!(tyx_to_typst::tyx_to_typst(&restored), typst);
+}
+
+#[test]
+fn test_generated_typst_compiles() {
+    use proptest::test_runner::{Config, TestRunner};
+    use tinymist_project::{
+        CompileOnceArgs, EntryReader, TaskInputs, WorldProvider, base::ShadowApi,
+    };
+
+    let dir = tempfile::tempdir().unwrap();
+    crate::round_trip::write_assets(dir.path()).unwrap();
+    let root = std::fs::canonicalize(dir.path()).unwrap();
+    let main = root.join("main.typ");
+    std::fs::write(&main, "").unwrap();
+
+    let universe = CompileOnceArgs {
+        root: Some(root),
+        input: Some(main.to_str().unwrap().to_string()),
+        ..CompileOnceArgs::default()
+    }
+    .resolve()
+    .unwrap();
+    let entry = universe
+        .entry_state()
+        .try_select_path_in_workspace(&main)
+        .unwrap();
+
+    let mut runner = TestRunner::new(Config {
+        cases: 32,
+        ..Config::default()
+    });
+    runner
+        .run(&crate::round_trip::document(), |document| {
+            let typst = tyx_to_typst::tyx_to_typst(&document);
+            let mut world = universe.snapshot_with(Some(TaskInputs {
+                entry: entry.clone(),
+                ..TaskInputs::default()
+            }));
+            let main = world.main_id().unwrap();
+            world
+                .map_shadow_by_id(main, typst::foundations::Bytes::from_string(typst.clone()))
+                .unwrap();
+            let compiled = typst::compile::<typst::layout::PagedDocument>(&world);
+            proptest::prop_assert!(
+                compiled.output.is_ok(),
+                "{:?}\n{typst}",
+                compiled.output.err()
+            );
+            Ok(())
+        })
+        .unwrap();
+}
+
+#[test]
+fn test_markdown_blocks() {
+    use tyx_schema::{TextFormat, TyXNode, TyXNodeListType};
+
+    let document = crate::markdown_to_tyx(
+        "# Title\n\nSome **bold** and `code`,\nwrapped.\n\n- [x] done\n- [ ] todo\n\n1. one\n   - nested\n2. two\n\n```rust\nfn main() {}\n```\n\n| a | b |\n|---|---|\n| c | d |\n",
+    );
+    let TyXNode::Root { children, .. } = document.content.unwrap().root else {
+        panic!("expected a root");
+    };
+
+    let [
+        TyXNode::Heading { .. },
+        TyXNode::Paragraph {
+            children: paragraph,
+            ..
+        },
+        TyXNode::List {
+            children: tasks,
+            list_type: TyXNodeListType::Check,
+            ..
+        },
+        TyXNode::List {
+            children: items,
+            list_type: TyXNodeListType::Number,
+            start: 1,
+            ..
+        },
+        TyXNode::Code {
+            language: Some(language),
+            ..
+        },
+        TyXNode::Table { children: rows, .. },
+    ] = children.as_slice()
+    else {
+        panic!("unexpected blocks: {children:?}");
+    };
+    assert!(matches!(
+        &paragraph[1],
+        TyXNode::Text { text, format, .. } if text == "bold" && *format == TextFormat::BOLD
+    ));
+    assert!(matches!(
+        &paragraph[3],
+        TyXNode::Text { format, .. } if *format == TextFormat::CODE
+    ));
+    assert!(matches!(
+        &paragraph[4],
+        TyXNode::Text { text, .. } if text == ", wrapped."
+    ));
+    assert!(matches!(
+        tasks.as_slice(),
+        [
+            TyXNode::Listitem {
+                checked: Some(true),
+                ..
+            },
+            TyXNode::Listitem {
+                checked: Some(false),
+                ..
+            },
+        ]
+    ));
+    assert!(matches!(
+        items.as_slice(),
+        [
+            TyXNode::Listitem { .. },
+            TyXNode::Listitem { children: nested, .. },
+            TyXNode::Listitem { .. },
+        ] if matches!(nested.as_slice(), [TyXNode::List { .. }])
+    ));
+    assert_eq!(language, "rust");
+    assert_eq!(rows.len(), 2);
+}
+
+#[test]
+fn test_markdown_empty_document() {
+    let document = crate::markdown_to_tyx("");
+    let Some(tyx_schema::TyXDocumentContent {
+        root: tyx_schema::TyXNode::Root { children, .. },
+    }) = document.content
+    else {
+        panic!("expected a root");
+    };
+    assert!(matches!(
+        children.as_slice(),
+        [tyx_schema::TyXNode::Paragraph { .. }]
+    ));
+}
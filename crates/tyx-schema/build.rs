@@ -1,5 +1,5 @@
 //! Based on https://github.com/oxidecomputer/typify/blob/main/example-build/build.rs
-use typify::{TypeSpace, TypeSpaceSettings};
+use typify::{TypeSpace, TypeSpaceImpl, TypeSpaceSettings};
 
 const SCHEMA_PATH: &str = "../../schemas/tyx.schema.json";
 
@@ -12,7 +12,14 @@ fn main() {
     let content = std::fs::read_to_string(SCHEMA_PATH).unwrap();
     let schema = serde_json::from_str::<schemars::schema::RootSchema>(&content).unwrap();
 
-    let mut type_space = TypeSpace::new(&TypeSpaceSettings::default());
+    let mut settings = TypeSpaceSettings::default();
+    // Text formats are a bitmask, which JSON schemas can't describe
+    settings.with_replacement(
+        "TyXTextFormat",
+        "crate::TextFormat",
+        [TypeSpaceImpl::Default].into_iter(),
+    );
+    let mut type_space = TypeSpace::new(&settings);
     type_space.add_root_schema(schema).unwrap();
 
     let contents =
@@ -7,30 +7,76 @@ mod generated {
 
 pub use generated::*;
 
-/// Masks for text format.
-/// See https://github.com/facebook/lexical/blob/cde863d444adf9c189626c789b53657c3352dbb1/packages/lexical/src/LexicalConstants.ts#L106.
-#[allow(dead_code)]
-pub enum TextFormat {
-    /// Bold.
-    Bold = 1,
-    /// Italic.
-    Italic = 1 << 1,
-    /// Strikethrough.
-    Strikethrough = 1 << 2,
-    /// Underline.
-    Underline = 1 << 3,
-    /// Code.
-    Code = 1 << 4,
-    /// Subscript.
-    Subscript = 1 << 5,
-    /// Superscript.
-    Superscript = 1 << 6,
-    /// Highlight.
-    Highlight = 1 << 7,
-    /// Lowercase.
-    Lowercase = 1 << 8,
-    /// Uppercase.
-    Uppercase = 1 << 9,
-    /// Capitalize.
-    Capitalize = 1 << 10,
+mod migrations;
+pub use migrations::{MigrationError, MigrationReport, Version, migrate};
+
+mod style;
+pub use style::{Color, FontSize, FontSizeUnit, TextStyle};
+
+mod validation;
+pub use validation::{Issue, Severity, validate};
+
+mod visit;
+pub use visit::{
+    Descendants, Visitor, VisitorMut, walk_document, walk_document_mut, walk_node, walk_node_mut,
+    walk_value, walk_value_mut,
+};
+
+bitflags::bitflags! {
+    /// The formats of text, serialized as Lexical's bitmask.
+    /// See https://github.com/facebook/lexical/blob/cde863d444adf9c189626c789b53657c3352dbb1/packages/lexical/src/LexicalConstants.ts#L106.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub struct TextFormat: i64 {
+        /// Bold.
+        const BOLD = 1;
+        /// Italic.
+        const ITALIC = 1 << 1;
+        /// Strikethrough.
+        const STRIKETHROUGH = 1 << 2;
+        /// Underline.
+        const UNDERLINE = 1 << 3;
+        /// Code.
+        const CODE = 1 << 4;
+        /// Subscript.
+        const SUBSCRIPT = 1 << 5;
+        /// Superscript.
+        const SUPERSCRIPT = 1 << 6;
+        /// Highlight.
+        const HIGHLIGHT = 1 << 7;
+        /// Lowercase.
+        const LOWERCASE = 1 << 8;
+        /// Uppercase.
+        const UPPERCASE = 1 << 9;
+        /// Capitalize.
+        const CAPITALIZE = 1 << 10;
+    }
+}
+
+impl serde::Serialize for TextFormat {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.bits().serialize(serializer)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for TextFormat {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // Unknown formats are kept, so that they survive a round trip
+        i64::deserialize(deserializer).map(Self::from_bits_retain)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_format_serde() {
+        let format = TextFormat::BOLD | TextFormat::HIGHLIGHT;
+        assert_eq!(serde_json::to_string(&format).unwrap(), "129");
+        assert_eq!(serde_json::from_str::<TextFormat>("129").unwrap(), format);
+        // Formats unknown to this version are kept
+        let unknown = serde_json::from_str::<TextFormat>("2049").unwrap();
+        assert!(unknown.contains(TextFormat::BOLD));
+        assert_eq!(serde_json::to_string(&unknown).unwrap(), "2049");
+    }
 }
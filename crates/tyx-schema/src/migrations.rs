@@ -0,0 +1,305 @@
+//! Migrations of serialized TyX documents saved by older versions of TyX.
+//!
+//! Documents are migrated as JSON, before being deserialized, by applying every
+//! migration introduced after the version the document was saved with, in
+//! order, and setting its version to the current one.
+use std::{cmp::Ordering, fmt};
+
+use serde_json::{Map, Value};
+
+/// A version of TyX, e.g. `0.2.18`, ignoring any pre-release or build metadata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    /// The major version.
+    pub major: u64,
+    /// The minor version.
+    pub minor: u64,
+    /// The patch version.
+    pub patch: u64,
+}
+
+impl Version {
+    /// Creates a version from its components.
+    pub const fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Parses a version such as `0.2.18` or `0.3.0-beta.1`.
+    pub fn parse(version: &str) -> Option<Self> {
+        let version = version.split(['-', '+']).next()?;
+        let mut components = version.split('.').map(|component| component.parse().ok());
+        let version = Self::new(
+            components.next()??,
+            components.next()??,
+            components.next()??,
+        );
+        components.next().is_none().then_some(version)
+    }
+
+    /// The version of this build of TyX.
+    pub fn current() -> Self {
+        Self::parse(tyx_version::VERSION).expect("the TyX version is malformed")
+    }
+
+    /// Whether documents saved with this version can be read by the given
+    /// version, i.e. whether it isn't a newer major version, or a newer minor
+    /// version before 1.0.
+    pub fn is_compatible_with(&self, other: &Self) -> bool {
+        match self.major.cmp(&other.major) {
+            Ordering::Less => true,
+            Ordering::Greater => false,
+            Ordering::Equal => self.major > 0 || self.minor <= other.minor,
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// The oldest version whose documents can be migrated, as documents saved with
+/// TyX 0.1 have an entirely different format.
+const OLDEST: Version = Version::new(0, 2, 0);
+
+/// A change of the document format, applied to documents saved before it.
+struct Migration {
+    /// The first version saving documents in the new format.
+    version: Version,
+    /// A short description of the change, for the migration report.
+    description: &'static str,
+    /// Migrates the document, returning whether anything changed.
+    migrate: fn(&mut Map<String, Value>) -> bool,
+}
+
+/// The migrations, ordered by version.
+const MIGRATIONS: &[Migration] = &[Migration {
+    version: Version::new(0, 2, 12),
+    description: "removed null fields, which are now left out",
+    migrate: remove_nulls,
+}];
+
+/// Removes the fields set to `null` from the object and its descendants.
+fn remove_nulls(object: &mut Map<String, Value>) -> bool {
+    let len = object.len();
+    object.retain(|_, value| !value.is_null());
+    let mut changed = object.len() != len;
+    for value in object.values_mut() {
+        changed |= remove_nulls_in(value);
+    }
+    changed
+}
+
+/// Removes the fields set to `null` from the objects in the value.
+fn remove_nulls_in(value: &mut Value) -> bool {
+    match value {
+        Value::Object(object) => remove_nulls(object),
+        Value::Array(values) => values
+            .iter_mut()
+            .fold(false, |changed, value| remove_nulls_in(value) | changed),
+        _ => false,
+    }
+}
+
+/// What [`migrate`] changed in a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationReport {
+    /// The version the document was saved with, if it had one.
+    pub from: Option<Version>,
+    /// The version the document was migrated to.
+    pub to: Version,
+    /// The descriptions of the migrations which changed the document, in the
+    /// order they were applied.
+    pub changes: Vec<&'static str>,
+}
+
+impl MigrationReport {
+    /// Whether the document didn't need any change besides its version.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl fmt::Display for MigrationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.from {
+            Some(from) => write!(f, "migrated from TyX {from} to {}", self.to)?,
+            None => write!(f, "migrated to TyX {}", self.to)?,
+        }
+        if !self.changes.is_empty() {
+            write!(f, ": {}", self.changes.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// An error raised while migrating a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MigrationError {
+    /// The document isn't a JSON object.
+    NotAnObject,
+    /// The document's version isn't a string, or isn't a version.
+    InvalidVersion(String),
+    /// The document was saved with an incompatible newer version of TyX.
+    NewerVersion {
+        /// The version the document was saved with.
+        version: Version,
+        /// The version of this build of TyX.
+        current: Version,
+    },
+    /// The document was saved with a version of TyX too old to migrate.
+    UnsupportedVersion(Version),
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotAnObject => write!(f, "a TyX document must be a JSON object"),
+            Self::InvalidVersion(version) => write!(f, "invalid TyX version {version}"),
+            Self::NewerVersion { version, current } => write!(
+                f,
+                "the document was saved with TyX {version}, which is newer than TyX {current}, update TyX to open it"
+            ),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "the document was saved with TyX {version}, whose documents can't be opened since TyX {OLDEST}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+/// Migrates a serialized TyX document to the current version of TyX.
+///
+/// Documents without a version, or with an empty one, are assumed to be from
+/// the oldest version which can be migrated. Documents from the current or a
+/// compatible newer version are only given the current version.
+pub fn migrate(document: &mut Value) -> Result<MigrationReport, MigrationError> {
+    let object = document
+        .as_object_mut()
+        .ok_or(MigrationError::NotAnObject)?;
+    let from = match object.get("version") {
+        None => None,
+        Some(Value::String(version)) if version.is_empty() => None,
+        Some(Value::String(version)) => Some(
+            Version::parse(version)
+                .ok_or_else(|| MigrationError::InvalidVersion(version.clone()))?,
+        ),
+        Some(version) => return Err(MigrationError::InvalidVersion(version.to_string())),
+    };
+
+    let current = Version::current();
+    if let Some(version) = from {
+        if !version.is_compatible_with(&current) {
+            return Err(MigrationError::NewerVersion { version, current });
+        }
+        if version < OLDEST {
+            return Err(MigrationError::UnsupportedVersion(version));
+        }
+    }
+
+    let mut changes = Vec::new();
+    for migration in MIGRATIONS {
+        if from.is_none_or(|version| version < migration.version) && (migration.migrate)(object) {
+            changes.push(migration.description);
+        }
+    }
+    object.insert("version".into(), Value::String(tyx_version::VERSION.into()));
+
+    Ok(MigrationReport {
+        from,
+        to: current,
+        changes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(Version::parse("0.2.18"), Some(Version::new(0, 2, 18)));
+        assert_eq!(Version::parse("1.0.0-beta.1"), Some(Version::new(1, 0, 0)));
+        assert_eq!(Version::parse("0.2"), None);
+        assert_eq!(Version::parse("0.2.18.1"), None);
+        assert_eq!(Version::parse("latest"), None);
+    }
+
+    #[test]
+    fn test_compatibility() {
+        let current = Version::new(0, 2, 18);
+        assert!(Version::new(0, 2, 30).is_compatible_with(&current));
+        assert!(Version::new(0, 1, 0).is_compatible_with(&current));
+        assert!(!Version::new(0, 3, 0).is_compatible_with(&current));
+        assert!(!Version::new(1, 0, 0).is_compatible_with(&current));
+        assert!(Version::new(1, 5, 0).is_compatible_with(&Version::new(1, 2, 0)));
+    }
+
+    #[test]
+    fn test_removes_nulls_from_old_documents() {
+        let mut document = json!({
+            "version": "0.2.11",
+            "settings": {"language": null},
+            "content": {"root": {"type": "root", "direction": null, "children": [
+                {"type": "paragraph", "direction": null, "children": []}
+            ]}}
+        });
+        let report = migrate(&mut document).unwrap();
+        assert_eq!(report.from, Some(Version::new(0, 2, 11)));
+        assert_eq!(report.changes.len(), 1);
+        assert_eq!(
+            document,
+            json!({
+                "version": tyx_version::VERSION,
+                "settings": {},
+                "content": {"root": {"type": "root", "children": [
+                    {"type": "paragraph", "children": []}
+                ]}}
+            })
+        );
+    }
+
+    #[test]
+    fn test_current_documents_are_unchanged() {
+        let mut document = json!({"version": tyx_version::VERSION, "preamble": ""});
+        let original = document.clone();
+        let report = migrate(&mut document).unwrap();
+        assert!(report.is_empty());
+        assert_eq!(document, original);
+    }
+
+    #[test]
+    fn test_newer_documents_are_refused() {
+        let current = Version::current();
+        let newer = Version::new(current.major + 1, 0, 0);
+        let mut document = json!({"version": newer.to_string()});
+        assert_eq!(
+            migrate(&mut document),
+            Err(MigrationError::NewerVersion {
+                version: newer,
+                current
+            })
+        );
+    }
+
+    #[test]
+    fn test_invalid_documents_are_refused() {
+        assert_eq!(
+            migrate(&mut json!({"version": "0.1.17"})),
+            Err(MigrationError::UnsupportedVersion(Version::new(0, 1, 17)))
+        );
+        assert_eq!(
+            migrate(&mut json!({"version": 1})),
+            Err(MigrationError::InvalidVersion("1".into()))
+        );
+        assert_eq!(migrate(&mut json!([])), Err(MigrationError::NotAnObject));
+    }
+}
@@ -0,0 +1,375 @@
+//! Parsing of the inline CSS styles Lexical stores on text nodes, such as
+//! `font-size: 12pt; color: #ff0000`.
+use std::fmt;
+
+/// The basic named CSS colors.
+const NAMED_COLORS: &[(&str, Color)] = &[
+    ("black", Color::rgb(0x00, 0x00, 0x00)),
+    ("silver", Color::rgb(0xc0, 0xc0, 0xc0)),
+    ("gray", Color::rgb(0x80, 0x80, 0x80)),
+    ("grey", Color::rgb(0x80, 0x80, 0x80)),
+    ("white", Color::rgb(0xff, 0xff, 0xff)),
+    ("maroon", Color::rgb(0x80, 0x00, 0x00)),
+    ("red", Color::rgb(0xff, 0x00, 0x00)),
+    ("purple", Color::rgb(0x80, 0x00, 0x80)),
+    ("fuchsia", Color::rgb(0xff, 0x00, 0xff)),
+    ("green", Color::rgb(0x00, 0x80, 0x00)),
+    ("lime", Color::rgb(0x00, 0xff, 0x00)),
+    ("olive", Color::rgb(0x80, 0x80, 0x00)),
+    ("yellow", Color::rgb(0xff, 0xff, 0x00)),
+    ("navy", Color::rgb(0x00, 0x00, 0x80)),
+    ("blue", Color::rgb(0x00, 0x00, 0xff)),
+    ("teal", Color::rgb(0x00, 0x80, 0x80)),
+    ("aqua", Color::rgb(0x00, 0xff, 0xff)),
+    ("orange", Color::rgb(0xff, 0xa5, 0x00)),
+    (
+        "transparent",
+        Color {
+            alpha: 0,
+            ..Color::rgb(0x00, 0x00, 0x00)
+        },
+    ),
+];
+
+/// A CSS color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Color {
+    /// The red channel.
+    pub red: u8,
+    /// The green channel.
+    pub green: u8,
+    /// The blue channel.
+    pub blue: u8,
+    /// The opacity, from 0 for transparent to 255 for opaque.
+    pub alpha: u8,
+}
+
+impl Color {
+    /// Creates an opaque color.
+    pub const fn rgb(red: u8, green: u8, blue: u8) -> Self {
+        Self {
+            red,
+            green,
+            blue,
+            alpha: 255,
+        }
+    }
+
+    /// Parses a hexadecimal color such as `#f00` or `#ff000080`, an `rgb()` or
+    /// `rgba()` color, or a basic named color.
+    pub fn parse(color: &str) -> Option<Self> {
+        let color = color.trim().to_ascii_lowercase();
+        if let Some(hex) = color.strip_prefix('#') {
+            return Self::parse_hex(hex);
+        }
+        if let Some(channels) = color
+            .strip_prefix("rgba(")
+            .or_else(|| color.strip_prefix("rgb("))
+        {
+            return Self::parse_channels(channels.strip_suffix(')')?);
+        }
+        NAMED_COLORS
+            .iter()
+            .find(|(name, _)| *name == color)
+            .map(|(_, color)| *color)
+    }
+
+    /// Parses the digits of a hexadecimal color.
+    fn parse_hex(hex: &str) -> Option<Self> {
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let digits = match hex.len() {
+            3 | 4 => hex.chars().flat_map(|c| [c, c]).collect(),
+            6 | 8 => hex.to_string(),
+            _ => return None,
+        };
+        let channel = |i: usize| u8::from_str_radix(digits.get(2 * i..2 * i + 2)?, 16).ok();
+        Some(Self {
+            red: channel(0)?,
+            green: channel(1)?,
+            blue: channel(2)?,
+            alpha: channel(3).unwrap_or(255),
+        })
+    }
+
+    /// Parses the channels of an `rgb()` color, separated by commas or spaces,
+    /// with an optional alpha.
+    fn parse_channels(channels: &str) -> Option<Self> {
+        let channels = channels
+            .split([',', '/', ' '])
+            .filter(|channel| !channel.is_empty())
+            .collect::<Vec<_>>();
+        let (red, green, blue, alpha) = match channels.as_slice() {
+            [red, green, blue] => (red, green, blue, None),
+            [red, green, blue, alpha] => (red, green, blue, Some(alpha)),
+            _ => return None,
+        };
+        let channel = |channel: &str| match channel.strip_suffix('%') {
+            Some(percent) => parse_number(percent).map(|percent| percent / 100.0 * 255.0),
+            None => parse_number(channel),
+        };
+        let alpha = match alpha {
+            Some(alpha) => match alpha.strip_suffix('%') {
+                Some(percent) => parse_number(percent)? / 100.0,
+                None => parse_number(alpha)?,
+            },
+            None => 1.0,
+        };
+        let byte = |value: f64| value.round().clamp(0.0, 255.0) as u8;
+        Some(Self {
+            red: byte(channel(red)?),
+            green: byte(channel(green)?),
+            blue: byte(channel(blue)?),
+            alpha: byte(alpha * 255.0),
+        })
+    }
+}
+
+impl fmt::Display for Color {
+    /// Formats the color in hexadecimal, e.g. `#ff0000`, with its alpha only
+    /// if it isn't opaque.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.red, self.green, self.blue)?;
+        if self.alpha != 255 {
+            write!(f, "{:02x}", self.alpha)?;
+        }
+        Ok(())
+    }
+}
+
+/// The unit of a [`FontSize`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FontSizeUnit {
+    /// Points.
+    Pt,
+    /// CSS pixels, i.e. 0.75 points.
+    Px,
+    /// Relative to the size of the surrounding text.
+    Em,
+    /// Relative to the size of the document's text.
+    Rem,
+    /// A percentage of the size of the surrounding text.
+    Percent,
+}
+
+/// A CSS font size, e.g. `12pt` or `1.5em`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FontSize {
+    /// The size, in the unit.
+    pub value: f64,
+    /// The unit of the size.
+    pub unit: FontSizeUnit,
+}
+
+impl FontSize {
+    /// Parses a non-negative font size in `pt`, `px`, `em`, `rem` or `%`.
+    pub fn parse(size: &str) -> Option<Self> {
+        let size = size.trim().to_ascii_lowercase();
+        // `rem` is checked before `em`, which it ends with
+        let (value, unit) = [
+            ("pt", FontSizeUnit::Pt),
+            ("px", FontSizeUnit::Px),
+            ("rem", FontSizeUnit::Rem),
+            ("em", FontSizeUnit::Em),
+            ("%", FontSizeUnit::Percent),
+        ]
+        .into_iter()
+        .find_map(|(suffix, unit)| Some((size.strip_suffix(suffix)?, unit)))?;
+        let value = parse_number(value).filter(|value| *value >= 0.0)?;
+        Some(Self { value, unit })
+    }
+}
+
+impl fmt::Display for FontSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unit = match self.unit {
+            FontSizeUnit::Pt => "pt",
+            FontSizeUnit::Px => "px",
+            FontSizeUnit::Em => "em",
+            FontSizeUnit::Rem => "rem",
+            FontSizeUnit::Percent => "%",
+        };
+        write!(f, "{}{unit}", self.value)
+    }
+}
+
+/// The inline style of a text node, as far as TyX understands it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TextStyle {
+    /// The `color` of the text.
+    pub color: Option<Color>,
+    /// The `background-color` of the text.
+    pub background_color: Option<Color>,
+    /// The `font-family` of the text, as a list of fallbacks, which may
+    /// include generic families such as `serif`.
+    pub font_families: Vec<String>,
+    /// The `font-size` of the text.
+    pub font_size: Option<FontSize>,
+}
+
+impl TextStyle {
+    /// Parses an inline CSS style, returning the style along with the
+    /// messages of the declarations it ignores, e.g. unknown properties or
+    /// invalid values.
+    pub fn parse(style: &str) -> (Self, Vec<String>) {
+        let mut result = Self::default();
+        let mut ignored = Vec::new();
+        for declaration in style.split(';').map(str::trim) {
+            if declaration.is_empty() {
+                continue;
+            }
+            let Some((property, value)) = declaration.split_once(':') else {
+                ignored.push(format!("invalid style declaration `{declaration}`"));
+                continue;
+            };
+            let property = property.trim().to_ascii_lowercase();
+            let value = value.trim();
+            let value = value.strip_suffix("!important").unwrap_or(value).trim();
+            let valid = match property.as_str() {
+                "color" => Color::parse(value).map(|color| result.color = Some(color)),
+                "background-color" => {
+                    Color::parse(value).map(|color| result.background_color = Some(color))
+                }
+                "font-family" => parse_font_families(value)
+                    .map(|font_families| result.font_families = font_families),
+                "font-size" => FontSize::parse(value).map(|size| result.font_size = Some(size)),
+                _ => {
+                    ignored.push(format!("unsupported style property `{property}`"));
+                    continue;
+                }
+            };
+            if valid.is_none() {
+                ignored.push(format!("invalid `{property}` value `{value}`"));
+            }
+        }
+        (result, ignored)
+    }
+}
+
+impl fmt::Display for TextStyle {
+    /// Formats the style as inline CSS, quoting the font families which
+    /// contain spaces.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut declarations = Vec::new();
+        if let Some(color) = self.color {
+            declarations.push(format!("color: {color}"));
+        }
+        if let Some(color) = self.background_color {
+            declarations.push(format!("background-color: {color}"));
+        }
+        if !self.font_families.is_empty() {
+            let families = self
+                .font_families
+                .iter()
+                .map(|family| {
+                    if family.contains(char::is_whitespace) {
+                        format!("\"{family}\"")
+                    } else {
+                        family.clone()
+                    }
+                })
+                .collect::<Vec<_>>();
+            declarations.push(format!("font-family: {}", families.join(", ")));
+        }
+        if let Some(size) = self.font_size {
+            declarations.push(format!("font-size: {size}"));
+        }
+        write!(f, "{}", declarations.join("; "))
+    }
+}
+
+/// Parses a comma separated list of font families, which may be quoted.
+fn parse_font_families(families: &str) -> Option<Vec<String>> {
+    families
+        .split(',')
+        .map(|family| {
+            let family = family.trim();
+            let family = ['"', '\'']
+                .into_iter()
+                .find_map(|quote| family.strip_prefix(quote)?.strip_suffix(quote))
+                .unwrap_or(family);
+            (!family.is_empty()).then(|| family.to_string())
+        })
+        .collect()
+}
+
+/// Parses a finite number.
+fn parse_number(number: &str) -> Option<f64> {
+    number
+        .trim()
+        .parse()
+        .ok()
+        .filter(|number: &f64| number.is_finite())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_colors() {
+        let red = Color::rgb(0xff, 0x00, 0x00);
+        assert_eq!(Color::parse("#f00"), Some(red));
+        assert_eq!(Color::parse("#FF0000"), Some(red));
+        assert_eq!(Color::parse("rgb(255, 0, 0)"), Some(red));
+        assert_eq!(Color::parse("rgb(100% 0% 0%)"), Some(red));
+        assert_eq!(Color::parse("Red"), Some(red));
+        assert_eq!(
+            Color::parse("rgba(255, 0, 0, 0.5)").map(|color| color.to_string()),
+            Some("#ff000080".into())
+        );
+        assert_eq!(
+            Color::parse("#ff000080").map(|color| color.to_string()),
+            Some("#ff000080".into())
+        );
+        assert_eq!(Color::parse("#f000"), Some(Color { alpha: 0, ..red }));
+        assert_eq!(Color::parse("#ff00000"), None);
+        assert_eq!(Color::parse("rgb(255, 0)"), None);
+        assert_eq!(Color::parse("var(--red)"), None);
+    }
+
+    #[test]
+    fn test_parse_font_sizes() {
+        let size = |value, unit| Some(FontSize { value, unit });
+        assert_eq!(FontSize::parse("12pt"), size(12.0, FontSizeUnit::Pt));
+        assert_eq!(FontSize::parse("15px"), size(15.0, FontSizeUnit::Px));
+        assert_eq!(FontSize::parse("1.5em"), size(1.5, FontSizeUnit::Em));
+        assert_eq!(FontSize::parse("2rem"), size(2.0, FontSizeUnit::Rem));
+        assert_eq!(FontSize::parse("120%"), size(120.0, FontSizeUnit::Percent));
+        assert_eq!(FontSize::parse("-1pt"), None);
+        assert_eq!(FontSize::parse("12"), None);
+        assert_eq!(FontSize::parse("larger"), None);
+    }
+
+    #[test]
+    fn test_parse_style() {
+        let (style, ignored) = TextStyle::parse(
+            "font-size: 15px; color: #d0021b; background-color: rgb(255, 255, 0) !important; font-family: \"Times New Roman\", serif; text-decoration: underline; font-size: big",
+        );
+        assert_eq!(
+            style,
+            TextStyle {
+                color: Some(Color::rgb(0xd0, 0x02, 0x1b)),
+                background_color: Some(Color::rgb(0xff, 0xff, 0x00)),
+                font_families: vec!["Times New Roman".into(), "serif".into()],
+                font_size: Some(FontSize {
+                    value: 15.0,
+                    unit: FontSizeUnit::Px
+                }),
+            }
+        );
+        assert_eq!(
+            ignored,
+            [
+                "unsupported style property `text-decoration`",
+                "invalid `font-size` value `big`"
+            ]
+        );
+        assert_eq!(
+            style.to_string(),
+            "color: #d0021b; background-color: #ffff00; font-family: \"Times New Roman\", serif; font-size: 15px"
+        );
+        assert_eq!(TextStyle::parse(&style.to_string()), (style, Vec::new()));
+    }
+}
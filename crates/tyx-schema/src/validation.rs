@@ -0,0 +1,459 @@
+//! Semantic validation of TyX documents, catching what the schema can't, such
+//! as lists of non-items, function calls missing required parameters or
+//! unsupported text styles.
+use std::{collections::HashMap, fmt};
+
+use crate::{
+    FunctionDefinition, TextStyle, TyXDocument, TyXLength, TyXNode, TyXValue, Visitor, walk_node,
+    walk_value,
+};
+
+/// The units of TyX lengths.
+const LENGTH_UNITS: &[&str] = &["pt", "mm", "cm", "in", "em", "fr", "%"];
+
+/// How serious an [`Issue`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The document can't be converted.
+    Error,
+    /// The document can be converted, but probably not as intended.
+    Warning,
+}
+
+/// A problem found in a TyX document by [`validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    /// The path of the offending node or value, e.g. `root/children/3` or
+    /// `settings/indentation`.
+    pub path: String,
+    /// How serious the issue is.
+    pub severity: Severity,
+    /// A description of the issue.
+    pub message: String,
+}
+
+impl Issue {
+    /// Whether the issue is an error.
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{severity} at `{}`: {}", self.path, self.message)
+    }
+}
+
+/// Collects the issues of a document while visiting it.
+struct Validator<'a> {
+    functions: &'a HashMap<String, FunctionDefinition>,
+    issues: Vec<Issue>,
+}
+
+impl Validator<'_> {
+    fn push(&mut self, path: &str, severity: Severity, message: impl Into<String>) {
+        self.issues.push(Issue {
+            path: path.into(),
+            severity,
+            message: message.into(),
+        });
+    }
+
+    fn node(&mut self, node: &TyXNode, path: &str) {
+        match node {
+            TyXNode::List { children, .. } => {
+                if children.is_empty() {
+                    self.push(path, Severity::Error, "a list must have at least one item");
+                }
+                for (i, child) in children.iter().enumerate() {
+                    if !matches!(child, TyXNode::Listitem { .. }) {
+                        self.push(
+                            &format!("{path}/children/{i}"),
+                            Severity::Error,
+                            "a list may only contain list items",
+                        );
+                    }
+                }
+            }
+            TyXNode::Table { children, .. } => {
+                if children.is_empty() {
+                    self.push(path, Severity::Error, "a table must have at least one row");
+                }
+                let mut columns = None;
+                for (i, child) in children.iter().enumerate() {
+                    let row_path = format!("{path}/children/{i}");
+                    let TyXNode::Tablerow { children: cells } = child else {
+                        self.push(&row_path, Severity::Error, "a table may only contain rows");
+                        continue;
+                    };
+                    match columns {
+                        None => columns = Some(cells.len()),
+                        Some(columns) if columns != cells.len() => self.push(
+                            &row_path,
+                            Severity::Warning,
+                            format!(
+                                "the row has {} cells while the first row has {columns}",
+                                cells.len()
+                            ),
+                        ),
+                        Some(_) => {}
+                    }
+                }
+            }
+            TyXNode::Tablerow { children } => {
+                for (i, child) in children.iter().enumerate() {
+                    if !matches!(child, TyXNode::Tablecell { .. }) {
+                        self.push(
+                            &format!("{path}/children/{i}"),
+                            Severity::Error,
+                            "a table row may only contain cells",
+                        );
+                    }
+                }
+            }
+            TyXNode::Functioncall {
+                name,
+                position_parameters,
+                named_parameters,
+            } => {
+                self.function_call(name, position_parameters, named_parameters, path);
+            }
+            TyXNode::Text {
+                style: Some(style), ..
+            } => {
+                for message in TextStyle::parse(style).1 {
+                    self.push(
+                        &format!("{path}/style"),
+                        Severity::Warning,
+                        format!("{message}, so it's ignored"),
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn function_call(
+        &mut self,
+        name: &Option<String>,
+        position_parameters: &[TyXValue],
+        named_parameters: &HashMap<String, TyXValue>,
+        path: &str,
+    ) {
+        let Some(name) = name.as_deref().filter(|name| !name.is_empty()) else {
+            self.push(path, Severity::Error, "a function call must have a name");
+            return;
+        };
+        // Functions defined elsewhere, e.g. in the preamble, can't be checked
+        let Some(definition) = self.functions.get(name) else {
+            return;
+        };
+
+        for (i, parameter) in definition.positional.iter().enumerate() {
+            if parameter.required == Some(true) && !position_parameters.get(i).is_some_and(is_set) {
+                let parameter = parameter
+                    .label
+                    .as_ref()
+                    .map_or_else(|| (i + 1).to_string(), |label| format!("`{label}`"));
+                self.push(
+                    path,
+                    Severity::Error,
+                    format!("`{name}` is missing the required parameter {parameter}"),
+                );
+            }
+        }
+        if position_parameters.len() > definition.positional.len() {
+            self.push(
+                path,
+                Severity::Warning,
+                format!(
+                    "`{name}` takes {} positional parameters but is given {}",
+                    definition.positional.len(),
+                    position_parameters.len()
+                ),
+            );
+        }
+        for parameter in &definition.named {
+            if parameter.required == Some(true)
+                && !named_parameters.get(&parameter.name).is_some_and(is_set)
+            {
+                self.push(
+                    path,
+                    Severity::Error,
+                    format!(
+                        "`{name}` is missing the required parameter `{}`",
+                        parameter.name
+                    ),
+                );
+            }
+        }
+        let mut unknown = named_parameters
+            .keys()
+            .filter(|parameter| {
+                !definition
+                    .named
+                    .iter()
+                    .any(|named| &named.name == *parameter)
+            })
+            .collect::<Vec<_>>();
+        unknown.sort();
+        for parameter in unknown {
+            self.push(
+                &format!("{path}/namedParameters/{parameter}"),
+                Severity::Warning,
+                format!("`{name}` has no parameter `{parameter}`"),
+            );
+        }
+    }
+
+    fn length(&mut self, unit: &Option<String>, value: &Option<String>, path: &str) {
+        match (unit, value.as_deref().filter(|value| !value.is_empty())) {
+            (None, None) => {}
+            (None, Some(_)) => self.push(
+                path,
+                Severity::Warning,
+                "the length has no unit, so it's ignored",
+            ),
+            (Some(unit), _) if !LENGTH_UNITS.contains(&unit.as_str()) => self.push(
+                path,
+                Severity::Error,
+                format!(
+                    "unknown length unit `{unit}`, expected one of {}",
+                    LENGTH_UNITS.join(", ")
+                ),
+            ),
+            (Some(_), Some(value)) if value.parse::<f64>().is_ok_and(f64::is_finite) => {}
+            (Some(_), value) => self.push(
+                path,
+                Severity::Error,
+                format!("invalid length value `{}`", value.unwrap_or_default()),
+            ),
+        }
+    }
+}
+
+impl Visitor for Validator<'_> {
+    fn visit_node(&mut self, node: &TyXNode, path: &str) {
+        self.node(node, path);
+        walk_node(self, node, path);
+    }
+
+    fn visit_value(&mut self, value: &TyXValue, path: &str) {
+        if let TyXValue::Length { unit, value } = value {
+            self.length(unit, value, path);
+        }
+        walk_value(self, value, path);
+    }
+}
+
+/// Whether a parameter value is set, as unset values are left out of the
+/// function call.
+fn is_set(value: &TyXValue) -> bool {
+    match value {
+        TyXValue::Length { unit, .. } => unit.is_some(),
+        TyXValue::Boolean { value } => value.is_some(),
+        TyXValue::Content { value } => value.is_some(),
+    }
+}
+
+/// Checks the document for problems its schema doesn't catch, such as lists of
+/// non-items, tables with uneven rows, function calls missing parameters
+/// required by their definition in the document settings, invalid lengths, or
+/// text styles which can't be exported.
+pub fn validate(document: &TyXDocument) -> Vec<Issue> {
+    let no_functions = HashMap::new();
+    let settings = document.settings.as_ref();
+    let mut validator = Validator {
+        functions: settings.map_or(&no_functions, |settings| &settings.functions),
+        issues: Vec::new(),
+    };
+
+    if let Some(TyXLength { unit, value }) =
+        settings.and_then(|settings| settings.indentation.as_ref())
+    {
+        validator.length(unit, value, "settings/indentation");
+    }
+    validator.visit_document(document);
+    validator.issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ParameterDescription, TextFormat, TyXDocumentContent, TyXDocumentSettings, TyXNodeListType,
+    };
+
+    fn document(children: Vec<TyXNode>) -> TyXDocument {
+        TyXDocument {
+            content: Some(TyXDocumentContent {
+                root: TyXNode::Root {
+                    children,
+                    direction: None,
+                },
+            }),
+            dirty: None,
+            filename: None,
+            id: None,
+            preamble: None,
+            schema: None,
+            settings: None,
+            version: String::new(),
+        }
+    }
+
+    fn row(cells: usize) -> TyXNode {
+        TyXNode::Tablerow {
+            children: (0..cells)
+                .map(|_| TyXNode::Tablecell {
+                    children: Vec::new(),
+                    direction: None,
+                })
+                .collect(),
+        }
+    }
+
+    fn length(unit: &str, value: &str) -> TyXValue {
+        TyXValue::Length {
+            unit: Some(unit.into()),
+            value: Some(value.into()),
+        }
+    }
+
+    fn call(name: &str, position_parameters: Vec<TyXValue>) -> TyXNode {
+        TyXNode::Functioncall {
+            name: Some(name.into()),
+            named_parameters: HashMap::new(),
+            position_parameters,
+        }
+    }
+
+    fn paths(issues: &[Issue]) -> Vec<(&str, Severity)> {
+        issues
+            .iter()
+            .map(|issue| (issue.path.as_str(), issue.severity))
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_document() {
+        let document = document(vec![
+            TyXNode::Table {
+                children: vec![row(2), row(2)],
+                direction: None,
+            },
+            call("h", vec![length("em", "1.5")]),
+        ]);
+        assert_eq!(validate(&document), Vec::new());
+    }
+
+    #[test]
+    fn test_lists() {
+        let document = document(vec![
+            TyXNode::List {
+                children: Vec::new(),
+                direction: None,
+                list_type: TyXNodeListType::Bullet,
+                start: 1,
+            },
+            TyXNode::List {
+                children: vec![TyXNode::Linebreak],
+                direction: None,
+                list_type: TyXNodeListType::Number,
+                start: 1,
+            },
+        ]);
+        assert_eq!(
+            paths(&validate(&document)),
+            [
+                ("root/children/0", Severity::Error),
+                ("root/children/1/children/0", Severity::Error)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tables() {
+        let document = document(vec![TyXNode::Table {
+            children: vec![row(2), row(3), TyXNode::Linebreak],
+            direction: None,
+        }]);
+        assert_eq!(
+            paths(&validate(&document)),
+            [
+                ("root/children/0/children/1", Severity::Warning),
+                ("root/children/0/children/2", Severity::Error)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_function_calls() {
+        let mut document = document(vec![
+            call("spacer", Vec::new()),
+            call("spacer", vec![length("pt", "2"), length("pt", "3")]),
+            call("spacer", vec![length("px", "2")]),
+        ]);
+        document.settings = Some(TyXDocumentSettings {
+            functions: HashMap::from([(
+                "spacer".into(),
+                FunctionDefinition {
+                    positional: vec![ParameterDescription {
+                        documentation: None,
+                        label: Some("amount".into()),
+                        required: Some(true),
+                        type_: "length".into(),
+                    }],
+                    ..FunctionDefinition::default()
+                },
+            )]),
+            indentation: Some(TyXLength {
+                unit: Some("em".into()),
+                value: Some("one".into()),
+            }),
+            ..TyXDocumentSettings::default()
+        });
+
+        let issues = validate(&document);
+        assert_eq!(
+            paths(&issues),
+            [
+                ("settings/indentation", Severity::Error),
+                ("root/children/0", Severity::Error),
+                ("root/children/1", Severity::Warning),
+                ("root/children/2/positionParameters/0", Severity::Error),
+            ]
+        );
+        assert_eq!(
+            issues[1].message,
+            "`spacer` is missing the required parameter `amount`"
+        );
+    }
+
+    #[test]
+    fn test_text_styles() {
+        let document = document(vec![TyXNode::Paragraph {
+            children: vec![TyXNode::Text {
+                format: TextFormat::empty(),
+                style: Some("font-size: 12pt; letter-spacing: 1px".into()),
+                text: "a".into(),
+            }],
+            direction: None,
+            format: crate::TyXNodeFormat::X,
+        }]);
+        let issues = validate(&document);
+        assert_eq!(
+            paths(&issues),
+            [("root/children/0/children/0/style", Severity::Warning)]
+        );
+        assert_eq!(
+            issues[0].message,
+            "unsupported style property `letter-spacing`, so it's ignored"
+        );
+    }
+}
@@ -0,0 +1,413 @@
+//! Traversal of TyX documents: the children of nodes, depth-first iterators
+//! over nodes and their paths, and visitors to query or transform documents.
+//!
+//! Paths are those used by the converters' errors and source maps, e.g.
+//! `root/children/2/children/0`. Besides their children, function calls contain
+//! the content of their parameters, at e.g. `positionParameters/0/value` or
+//! `namedParameters/body/value`. The code of Typst code nodes isn't traversed.
+use std::collections::HashMap;
+
+use crate::{TyXDocument, TyXNode, TyXValue};
+
+/// Appends a segment to a path, e.g. `children/0` to `root`.
+fn join(path: &str, segment: impl std::fmt::Display) -> String {
+    if path.is_empty() {
+        segment.to_string()
+    } else {
+        format!("{path}/{segment}")
+    }
+}
+
+/// Returns the parameters of a function call along with their path segments,
+/// the positional ones first and then the named ones, sorted by name.
+fn parameters<'a>(
+    position_parameters: &'a [TyXValue],
+    named_parameters: &'a HashMap<String, TyXValue>,
+) -> impl Iterator<Item = (String, &'a TyXValue)> {
+    let mut named = named_parameters.iter().collect::<Vec<_>>();
+    named.sort_by_key(|(name, _)| *name);
+    position_parameters
+        .iter()
+        .enumerate()
+        .map(|(i, value)| (format!("positionParameters/{i}"), value))
+        .chain(
+            named
+                .into_iter()
+                .map(|(name, value)| (format!("namedParameters/{name}"), value)),
+        )
+}
+
+impl TyXNode {
+    /// Returns the type of the node, as serialized in its `type` field.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Root { .. } => "root",
+            Self::Paragraph { .. } => "paragraph",
+            Self::Text { .. } => "text",
+            Self::Math { .. } => "math",
+            Self::Listitem { .. } => "listitem",
+            Self::List { .. } => "list",
+            Self::Code { .. } => "code",
+            Self::Quote { .. } => "quote",
+            Self::Table { .. } => "table",
+            Self::Tablerow { .. } => "tablerow",
+            Self::Tablecell { .. } => "tablecell",
+            Self::Linebreak { .. } => "linebreak",
+            Self::Horizontalrule { .. } => "horizontalrule",
+            Self::Typstcode { .. } => "typstcode",
+            Self::Image { .. } => "image",
+            Self::Link { .. } => "link",
+            Self::Heading { .. } => "heading",
+            Self::Functioncall { .. } => "functioncall",
+            Self::Tab { .. } => "tab",
+            Self::CodeHighlight { .. } => "code-highlight",
+        }
+    }
+
+    /// Returns the children of the node, which are empty for nodes which can't
+    /// have any.
+    pub fn children(&self) -> &[TyXNode] {
+        match self {
+            Self::Root { children, .. }
+            | Self::Paragraph { children, .. }
+            | Self::Listitem { children, .. }
+            | Self::List { children, .. }
+            | Self::Code { children, .. }
+            | Self::Quote { children, .. }
+            | Self::Table { children, .. }
+            | Self::Tablerow { children }
+            | Self::Tablecell { children, .. }
+            | Self::Link { children, .. }
+            | Self::Heading { children, .. } => children,
+            _ => &[],
+        }
+    }
+
+    /// Returns the children of the node, or `None` for nodes which can't have
+    /// any.
+    pub fn children_mut(&mut self) -> Option<&mut Vec<TyXNode>> {
+        match self {
+            Self::Root { children, .. }
+            | Self::Paragraph { children, .. }
+            | Self::Listitem { children, .. }
+            | Self::List { children, .. }
+            | Self::Code { children, .. }
+            | Self::Quote { children, .. }
+            | Self::Table { children, .. }
+            | Self::Tablerow { children }
+            | Self::Tablecell { children, .. }
+            | Self::Link { children, .. }
+            | Self::Heading { children, .. } => Some(children),
+            _ => None,
+        }
+    }
+
+    /// Iterates over the node and its descendants depth-first, along with
+    /// their paths relative to the node, the node's own path being empty.
+    pub fn descendants(&self) -> Descendants<'_> {
+        Descendants {
+            stack: vec![(String::new(), self)],
+        }
+    }
+}
+
+impl TyXDocument {
+    /// Iterates over the nodes of the document depth-first, along with their
+    /// paths, starting from its root at `root`.
+    pub fn nodes(&self) -> Descendants<'_> {
+        Descendants {
+            stack: self
+                .content
+                .iter()
+                .map(|content| (String::from("root"), &content.root))
+                .collect(),
+        }
+    }
+}
+
+/// A depth-first iterator over a node and its descendants, along with their
+/// paths, see [`TyXNode::descendants`] and [`TyXDocument::nodes`].
+#[derive(Debug, Clone)]
+pub struct Descendants<'a> {
+    stack: Vec<(String, &'a TyXNode)>,
+}
+
+impl<'a> Iterator for Descendants<'a> {
+    type Item = (String, &'a TyXNode);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (path, node) = self.stack.pop()?;
+        let start = self.stack.len();
+        for (i, child) in node.children().iter().enumerate() {
+            self.stack
+                .push((join(&path, format!("children/{i}")), child));
+        }
+        if let TyXNode::Functioncall {
+            position_parameters,
+            named_parameters,
+            ..
+        } = node
+        {
+            for (segment, value) in parameters(position_parameters, named_parameters) {
+                if let TyXValue::Content { value: Some(root) } = value {
+                    self.stack
+                        .push((join(&path, format!("{segment}/value")), root));
+                }
+            }
+        }
+        // Pushed in order, but popped in reverse order
+        self.stack[start..].reverse();
+        Some((path, node))
+    }
+}
+
+/// Visits the nodes and values of a document depth-first, e.g. to query it.
+///
+/// Every method visits the descendants of what it is given by default, so
+/// implementations overriding them should call the matching `walk_*` function
+/// to keep visiting them.
+pub trait Visitor {
+    /// Visits the document, i.e. its root at `root`.
+    fn visit_document(&mut self, document: &TyXDocument) {
+        walk_document(self, document);
+    }
+
+    /// Visits a node at the given path.
+    fn visit_node(&mut self, node: &TyXNode, path: &str) {
+        walk_node(self, node, path);
+    }
+
+    /// Visits a function call parameter at the given path.
+    fn visit_value(&mut self, value: &TyXValue, path: &str) {
+        walk_value(self, value, path);
+    }
+}
+
+/// Visits the root of the document.
+pub fn walk_document<V: Visitor + ?Sized>(visitor: &mut V, document: &TyXDocument) {
+    if let Some(content) = &document.content {
+        visitor.visit_node(&content.root, "root");
+    }
+}
+
+/// Visits the children of the node, or its parameters if it is a function
+/// call.
+pub fn walk_node<V: Visitor + ?Sized>(visitor: &mut V, node: &TyXNode, path: &str) {
+    for (i, child) in node.children().iter().enumerate() {
+        visitor.visit_node(child, &join(path, format!("children/{i}")));
+    }
+    if let TyXNode::Functioncall {
+        position_parameters,
+        named_parameters,
+        ..
+    } = node
+    {
+        for (segment, value) in parameters(position_parameters, named_parameters) {
+            visitor.visit_value(value, &join(path, segment));
+        }
+    }
+}
+
+/// Visits the content of the value, if it is a content value.
+pub fn walk_value<V: Visitor + ?Sized>(visitor: &mut V, value: &TyXValue, path: &str) {
+    if let TyXValue::Content { value: Some(root) } = value {
+        visitor.visit_node(root, &join(path, "value"));
+    }
+}
+
+/// Visits the nodes and values of a document depth-first, allowing to change
+/// them, e.g. to transform it.
+///
+/// Like [`Visitor`], implementations overriding its methods should call the
+/// matching `walk_*_mut` function to keep visiting the descendants.
+pub trait VisitorMut {
+    /// Visits the document, i.e. its root at `root`.
+    fn visit_document_mut(&mut self, document: &mut TyXDocument) {
+        walk_document_mut(self, document);
+    }
+
+    /// Visits a node at the given path.
+    fn visit_node_mut(&mut self, node: &mut TyXNode, path: &str) {
+        walk_node_mut(self, node, path);
+    }
+
+    /// Visits a function call parameter at the given path.
+    fn visit_value_mut(&mut self, value: &mut TyXValue, path: &str) {
+        walk_value_mut(self, value, path);
+    }
+}
+
+/// Visits the root of the document.
+pub fn walk_document_mut<V: VisitorMut + ?Sized>(visitor: &mut V, document: &mut TyXDocument) {
+    if let Some(content) = &mut document.content {
+        visitor.visit_node_mut(&mut content.root, "root");
+    }
+}
+
+/// Visits the children of the node, or its parameters if it is a function
+/// call.
+pub fn walk_node_mut<V: VisitorMut + ?Sized>(visitor: &mut V, node: &mut TyXNode, path: &str) {
+    if let Some(children) = node.children_mut() {
+        for (i, child) in children.iter_mut().enumerate() {
+            visitor.visit_node_mut(child, &join(path, format!("children/{i}")));
+        }
+    }
+    if let TyXNode::Functioncall {
+        position_parameters,
+        named_parameters,
+        ..
+    } = node
+    {
+        for (i, value) in position_parameters.iter_mut().enumerate() {
+            visitor.visit_value_mut(value, &join(path, format!("positionParameters/{i}")));
+        }
+        let mut named = named_parameters.iter_mut().collect::<Vec<_>>();
+        named.sort_by_key(|(name, _)| *name);
+        for (name, value) in named {
+            visitor.visit_value_mut(value, &join(path, format!("namedParameters/{name}")));
+        }
+    }
+}
+
+/// Visits the content of the value, if it is a content value.
+pub fn walk_value_mut<V: VisitorMut + ?Sized>(visitor: &mut V, value: &mut TyXValue, path: &str) {
+    if let TyXValue::Content { value: Some(root) } = value {
+        visitor.visit_node_mut(root, &join(path, "value"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TextFormat, TyXDocumentContent};
+
+    fn text(text: &str) -> TyXNode {
+        TyXNode::Text {
+            format: TextFormat::empty(),
+            style: None,
+            text: text.into(),
+        }
+    }
+
+    fn paragraph(children: Vec<TyXNode>) -> TyXNode {
+        TyXNode::Paragraph {
+            children,
+            direction: None,
+            format: crate::TyXNodeFormat::X,
+        }
+    }
+
+    fn document() -> TyXDocument {
+        TyXDocument {
+            content: Some(TyXDocumentContent {
+                root: TyXNode::Root {
+                    children: vec![
+                        paragraph(vec![text("a"), text("b")]),
+                        paragraph(vec![TyXNode::Functioncall {
+                            name: Some("footnote".into()),
+                            named_parameters: HashMap::new(),
+                            position_parameters: vec![TyXValue::Content {
+                                value: Some(paragraph(vec![text("c")])),
+                            }],
+                        }]),
+                    ],
+                    direction: None,
+                },
+            }),
+            dirty: None,
+            filename: None,
+            id: None,
+            preamble: None,
+            schema: None,
+            settings: None,
+            version: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_children() {
+        let mut paragraph = paragraph(vec![text("a")]);
+        assert_eq!(paragraph.children().len(), 1);
+        paragraph.children_mut().unwrap().push(text("b"));
+        assert_eq!(paragraph.children().len(), 2);
+
+        let mut text = text("a");
+        assert_eq!(text.kind(), "text");
+        assert!(text.children().is_empty());
+        assert!(text.children_mut().is_none());
+    }
+
+    #[test]
+    fn test_nodes_are_depth_first() {
+        let paths = document().nodes().map(|(path, _)| path).collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            [
+                "root",
+                "root/children/0",
+                "root/children/0/children/0",
+                "root/children/0/children/1",
+                "root/children/1",
+                "root/children/1/children/0",
+                "root/children/1/children/0/positionParameters/0/value",
+                "root/children/1/children/0/positionParameters/0/value/children/0",
+            ]
+        );
+
+        let root = document().content.unwrap().root;
+        let (path, _) = root.children()[0].descendants().nth(2).unwrap();
+        assert_eq!(path, "children/1");
+    }
+
+    #[test]
+    fn test_visitor() {
+        #[derive(Default)]
+        struct Texts(Vec<(String, String)>);
+        impl Visitor for Texts {
+            fn visit_node(&mut self, node: &TyXNode, path: &str) {
+                if let TyXNode::Text { text, .. } = node {
+                    self.0.push((path.into(), text.clone()));
+                }
+                walk_node(self, node, path);
+            }
+        }
+
+        let mut texts = Texts::default();
+        texts.visit_document(&document());
+        assert_eq!(
+            texts.0,
+            [
+                ("root/children/0/children/0".into(), "a".into()),
+                ("root/children/0/children/1".into(), "b".into()),
+                (
+                    "root/children/1/children/0/positionParameters/0/value/children/0".into(),
+                    "c".into()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_visitor_mut() {
+        struct Upper;
+        impl VisitorMut for Upper {
+            fn visit_node_mut(&mut self, node: &mut TyXNode, path: &str) {
+                if let TyXNode::Text { text, .. } = node {
+                    *text = text.to_uppercase();
+                }
+                walk_node_mut(self, node, path);
+            }
+        }
+
+        let mut document = document();
+        Upper.visit_document_mut(&mut document);
+        let texts = document
+            .nodes()
+            .filter_map(|(_, node)| match node {
+                TyXNode::Text { text, .. } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect::<String>();
+        assert_eq!(texts, "ABC");
+    }
+}
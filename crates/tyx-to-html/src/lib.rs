@@ -0,0 +1,575 @@
+//! Converts a TyX document to a self-contained HTML page.
+//!
+//! Math is kept as LaTeX from its `formula`, delimited by `\(...\)` or
+//! `\[...\]` so it can be typeset by e.g. MathJax or KaTeX, falling back to its
+//! Typst code. Typst code and function calls other than footnotes are kept as
+//! code.
+use std::fmt::Write;
+
+use tyx_schema::{
+    TextFormat, TyXDirection, TyXDirectionValue, TyXDocument, TyXNode, TyXNodeFormat,
+    TyXNodeListType, TyXNodeTag, TyXValue,
+};
+use tyx_to_typst::{
+    ConversionError, try_parse_tyx_document, try_stringify_function, try_validate_tyx_document,
+};
+
+/// The stylesheet embedded in every page.
+const STYLE: &str = "body { max-width: 50em; margin: 2em auto; padding: 0 1em; font-family: serif; line-height: 1.5; }
+pre { padding: 0.5em; background: #f5f5f5; overflow-x: auto; }
+table { border-collapse: collapse; }
+td { border: 1px solid #ccc; padding: 0.25em 0.5em; }
+.math.display { display: block; text-align: center; }
+.checklist { list-style: none; }
+.footnotes { border-top: 1px solid #ccc; font-size: 0.9em; }";
+
+/// Escapes text for HTML content and attribute values.
+fn escape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&#39;"),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// Returns the ` dir` attribute of the given direction, if any.
+fn dir(direction: &Option<TyXDirection>) -> &'static str {
+    match direction {
+        Some(TyXDirection(Some(TyXDirectionValue::Ltr))) => " dir=\"ltr\"",
+        Some(TyXDirection(Some(TyXDirectionValue::Rtl))) => " dir=\"rtl\"",
+        _ => "",
+    }
+}
+
+/// Returns the `style` attribute aligning a paragraph, if any.
+fn alignment(format: &TyXNodeFormat) -> String {
+    match format {
+        TyXNodeFormat::X => String::new(),
+        format => format!(" style=\"text-align: {format}\""),
+    }
+}
+
+/// Returns the tag of a heading.
+fn heading_tag(tag: &TyXNodeTag) -> &'static str {
+    match tag {
+        TyXNodeTag::H1 => "h1",
+        TyXNodeTag::H2 => "h2",
+        TyXNodeTag::H3 => "h3",
+        TyXNodeTag::H4 => "h4",
+        TyXNodeTag::H5 => "h5",
+        TyXNodeTag::H6 => "h6",
+    }
+}
+
+/// Returns the text of a node, used for code blocks and Typst code.
+fn node_to_text(node: &TyXNode) -> String {
+    match node {
+        TyXNode::Text { text, .. } | TyXNode::CodeHighlight { text } | TyXNode::Tab { text } => {
+            text.clone()
+        }
+        TyXNode::Linebreak => "\n".into(),
+        TyXNode::Root { children, .. } | TyXNode::Code { children, .. } => {
+            children.iter().map(node_to_text).collect()
+        }
+        TyXNode::Paragraph { children, .. } => {
+            children.iter().map(node_to_text).collect::<String>() + "\n"
+        }
+        _ => String::new(),
+    }
+}
+
+/// Applies the TyX text format and style to escaped text.
+fn apply_text_format(mut result: String, format: TextFormat, style: &Option<String>) -> String {
+    let tags = [
+        (TextFormat::CODE, "code"),
+        (TextFormat::SUBSCRIPT, "sub"),
+        (TextFormat::SUPERSCRIPT, "sup"),
+        (TextFormat::HIGHLIGHT, "mark"),
+        (TextFormat::STRIKETHROUGH, "s"),
+        (TextFormat::UNDERLINE, "u"),
+        (TextFormat::ITALIC, "em"),
+        (TextFormat::BOLD, "strong"),
+    ];
+    for (flag, tag) in tags {
+        if format.contains(flag) {
+            result = format!("<{tag}>{result}</{tag}>");
+        }
+    }
+
+    let transform = [
+        (TextFormat::LOWERCASE, "lowercase"),
+        (TextFormat::UPPERCASE, "uppercase"),
+        (TextFormat::CAPITALIZE, "capitalize"),
+    ]
+    .into_iter()
+    .find_map(|(flag, transform)| {
+        format
+            .contains(flag)
+            .then(|| format!("text-transform: {transform}"))
+    });
+    let style = [style.clone().filter(|style| !style.is_empty()), transform]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join("; ");
+    if !style.is_empty() {
+        result = format!("<span style=\"{}\">{result}</span>", escape(&style));
+    }
+    result
+}
+
+/// Converts TyX nodes to HTML, collecting footnotes along the way.
+#[derive(Default)]
+struct Writer {
+    html: String,
+    footnotes: Vec<String>,
+}
+
+impl Writer {
+    /// Writes the children of a node.
+    fn children(&mut self, children: &[TyXNode]) -> Result<(), ConversionError> {
+        for child in children {
+            self.node(child)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the children of a node into a separate string.
+    fn nested(&mut self, children: &[TyXNode]) -> Result<String, ConversionError> {
+        let html = std::mem::take(&mut self.html);
+        let result = self.children(children);
+        let nested = std::mem::replace(&mut self.html, html);
+        result.map(|()| nested)
+    }
+
+    /// Writes a list, nesting the lists held by items of their own into the
+    /// preceding item.
+    fn list(
+        &mut self,
+        children: &[TyXNode],
+        direction: &Option<TyXDirection>,
+        list_type: &TyXNodeListType,
+        start: i64,
+    ) -> Result<(), ConversionError> {
+        let (tag, attributes) = match list_type {
+            TyXNodeListType::Bullet => ("ul", String::new()),
+            TyXNodeListType::Number if start == 1 => ("ol", String::new()),
+            TyXNodeListType::Number => ("ol", format!(" start=\"{start}\"")),
+            TyXNodeListType::Check => ("ul", String::from(" class=\"checklist\"")),
+        };
+        write!(self.html, "<{tag}{attributes}{}>", dir(direction)).unwrap();
+
+        let mut open = false;
+        for child in children {
+            let TyXNode::Listitem {
+                checked, children, ..
+            } = child
+            else {
+                continue;
+            };
+            let nested = match children.as_slice() {
+                [
+                    TyXNode::List {
+                        children,
+                        direction,
+                        list_type,
+                        start,
+                    },
+                ] => Some((children, direction, list_type, *start)),
+                _ => None,
+            };
+            if !(nested.is_some() && open) {
+                if open {
+                    self.html.push_str("</li>");
+                }
+                self.html.push_str("<li>");
+                if *list_type == TyXNodeListType::Check && nested.is_none() {
+                    let checked = if *checked == Some(true) {
+                        " checked"
+                    } else {
+                        ""
+                    };
+                    write!(self.html, "<input type=\"checkbox\" disabled{checked}> ").unwrap();
+                }
+                open = true;
+            }
+            match nested {
+                Some((children, direction, list_type, start)) => {
+                    self.list(children, direction, list_type, start)?
+                }
+                None => self.children(children)?,
+            }
+        }
+        if open {
+            self.html.push_str("</li>");
+        }
+
+        write!(self.html, "</{tag}>").unwrap();
+        Ok(())
+    }
+
+    /// Writes a function call, as a footnote reference for footnotes and as
+    /// code otherwise.
+    fn function(
+        &mut self,
+        name: &Option<String>,
+        position_parameters: &[TyXValue],
+        named_parameters: &std::collections::HashMap<String, TyXValue>,
+    ) -> Result<(), ConversionError> {
+        if name.as_deref() == Some("footnote")
+            && let [
+                TyXValue::Content {
+                    value: Some(content),
+                },
+            ] = position_parameters
+        {
+            let footnote = self.nested(std::slice::from_ref(content))?;
+            self.footnotes.push(footnote);
+            let number = self.footnotes.len();
+            write!(
+                self.html,
+                "<sup id=\"footnote-ref-{number}\"><a href=\"#footnote-{number}\">{number}</a></sup>"
+            )
+            .unwrap();
+            return Ok(());
+        }
+
+        let code = try_stringify_function(name, position_parameters, named_parameters, true)?;
+        write!(self.html, "<code class=\"typst\">{}</code>", escape(&code)).unwrap();
+        Ok(())
+    }
+
+    /// Writes a node.
+    fn node(&mut self, node: &TyXNode) -> Result<(), ConversionError> {
+        match node {
+            TyXNode::Root { children, .. } => self.children(children)?,
+            TyXNode::Paragraph {
+                children,
+                direction,
+                format,
+            } => {
+                write!(self.html, "<p{}{}>", dir(direction), alignment(format)).unwrap();
+                self.children(children)?;
+                self.html.push_str("</p>\n");
+            }
+            TyXNode::Text {
+                format,
+                style,
+                text,
+            } => self
+                .html
+                .push_str(&apply_text_format(escape(text), *format, style)),
+            TyXNode::CodeHighlight { text } | TyXNode::Tab { text } => {
+                self.html.push_str(&escape(text))
+            }
+            TyXNode::Linebreak => self.html.push_str("<br>"),
+            TyXNode::Horizontalrule => self.html.push_str("<hr>\n"),
+            TyXNode::Math {
+                formula,
+                inline,
+                typst,
+            } => {
+                let inline = inline.unwrap_or(false);
+                let class = if inline {
+                    "math inline"
+                } else {
+                    "math display"
+                };
+                match formula {
+                    Some(formula) => {
+                        let (open, close) = if inline {
+                            ("\\(", "\\)")
+                        } else {
+                            ("\\[", "\\]")
+                        };
+                        write!(
+                            self.html,
+                            "<span class=\"{class}\">{open}{}{close}</span>",
+                            escape(formula.trim())
+                        )
+                        .unwrap();
+                    }
+                    None => write!(
+                        self.html,
+                        "<code class=\"{class} typst\">{}</code>",
+                        escape(typst.as_deref().unwrap_or_default().trim())
+                    )
+                    .unwrap(),
+                }
+            }
+            TyXNode::Heading { children, tag } => {
+                let tag = heading_tag(tag);
+                write!(self.html, "<{tag}>").unwrap();
+                self.children(children)?;
+                writeln!(self.html, "</{tag}>").unwrap();
+            }
+            TyXNode::Quote {
+                children,
+                direction,
+            } => {
+                write!(self.html, "<blockquote{}>", dir(direction)).unwrap();
+                self.children(children)?;
+                self.html.push_str("</blockquote>\n");
+            }
+            TyXNode::List {
+                children,
+                direction,
+                list_type,
+                start,
+            } => {
+                self.list(children, direction, list_type, *start)?;
+                self.html.push('\n');
+            }
+            TyXNode::Listitem { children, .. } => self.children(children)?,
+            TyXNode::Code { language, .. } => {
+                let class = language
+                    .as_ref()
+                    .map(|language| format!(" class=\"language-{}\"", escape(language)))
+                    .unwrap_or_default();
+                writeln!(
+                    self.html,
+                    "<pre dir=\"ltr\"><code{class}>{}</code></pre>",
+                    escape(&node_to_text(node))
+                )
+                .unwrap();
+            }
+            TyXNode::Table {
+                children,
+                direction,
+            } => {
+                write!(self.html, "<table{}>", dir(direction)).unwrap();
+                self.children(children)?;
+                self.html.push_str("</table>\n");
+            }
+            TyXNode::Tablerow { children } => {
+                self.html.push_str("<tr>");
+                self.children(children)?;
+                self.html.push_str("</tr>");
+            }
+            TyXNode::Tablecell {
+                children,
+                direction,
+            } => {
+                write!(self.html, "<td{}>", dir(direction)).unwrap();
+                self.children(children)?;
+                self.html.push_str("</td>");
+            }
+            TyXNode::Typstcode { text } => write!(
+                self.html,
+                "<code class=\"typst\">{}</code>",
+                escape(node_to_text(&text.editor_state.root).trim_end())
+            )
+            .unwrap(),
+            TyXNode::Image { src } => {
+                write!(self.html, "<img src=\"{}\" alt=\"\">", escape(src)).unwrap()
+            }
+            TyXNode::Link { children, url } => {
+                write!(self.html, "<a href=\"{}\">", escape(url)).unwrap();
+                self.children(children)?;
+                self.html.push_str("</a>");
+            }
+            TyXNode::Functioncall {
+                name,
+                named_parameters,
+                position_parameters,
+            } => self.function(name, position_parameters, named_parameters)?,
+        }
+        Ok(())
+    }
+}
+
+/// Converts a TyX document to an HTML page.
+///
+/// # Panics
+///
+/// Panics if a function call can't be converted, see [`try_tyx_to_html`].
+pub fn tyx_to_html(document: &TyXDocument) -> String {
+    try_tyx_to_html(document).unwrap_or_else(|err| panic!("{err}"))
+}
+
+/// Converts a TyX document to an HTML page, failing if the document is invalid
+/// or a function call can't be converted.
+pub fn try_tyx_to_html(document: &TyXDocument) -> Result<String, ConversionError> {
+    try_validate_tyx_document(document)?;
+    let mut writer = Writer::default();
+    let mut direction = &None;
+    if let Some(content) = &document.content {
+        if let TyXNode::Root {
+            direction: root_direction,
+            ..
+        } = &content.root
+        {
+            direction = root_direction;
+        }
+        writer.node(&content.root)?;
+    }
+
+    let language = document
+        .settings
+        .as_ref()
+        .and_then(|settings| settings.language.as_ref())
+        .map(|language| format!(" lang=\"{}\"", escape(language)))
+        .unwrap_or_default();
+    let title = document
+        .filename
+        .as_deref()
+        .and_then(|filename| filename.rsplit(['/', '\\']).next())
+        .map(|filename| filename.strip_suffix(".tyx").unwrap_or(filename))
+        .unwrap_or("Untitled");
+
+    let mut html = String::from("<!DOCTYPE html>\n");
+    writeln!(html, "<html{language}>").unwrap();
+    html.push_str("<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n");
+    writeln!(html, "<title>{}</title>", escape(title)).unwrap();
+    writeln!(html, "<style>\n{STYLE}\n</style>").unwrap();
+    html.push_str("</head>\n");
+    writeln!(html, "<body{}>", dir(direction)).unwrap();
+    html.push_str(&writer.html);
+    if !writer.footnotes.is_empty() {
+        html.push_str("<section class=\"footnotes\">\n<ol>\n");
+        for (i, footnote) in writer.footnotes.iter().enumerate() {
+            let number = i + 1;
+            writeln!(
+                html,
+                "<li id=\"footnote-{number}\">{footnote}<a href=\"#footnote-ref-{number}\">↩</a></li>"
+            )
+            .unwrap();
+        }
+        html.push_str("</ol>\n</section>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    Ok(html)
+}
+
+/// Converts the serialized TyX document to an HTML page.
+///
+/// # Panics
+///
+/// Panics if the document is malformed, see [`try_serialized_tyx_to_html`].
+pub fn serialized_tyx_to_html(document: &str) -> String {
+    try_serialized_tyx_to_html(document).unwrap_or_else(|err| panic!("{err}"))
+}
+
+/// Converts the serialized TyX document to an HTML page, failing if the
+/// document is malformed or can't be converted.
+pub fn try_serialized_tyx_to_html(document: &str) -> Result<String, ConversionError> {
+    let document = try_parse_tyx_document(document)?;
+    try_tyx_to_html(&document)
+}
+
+#[cfg(test)]
+mod tests {
+    use tyx_schema::{
+        TextFormat, TyXDirection, TyXDirectionValue, TyXDocument, TyXDocumentContent, TyXNode,
+        TyXNodeFormat, TyXNodeListType,
+    };
+
+    fn document(children: Vec<TyXNode>) -> TyXDocument {
+        TyXDocument {
+            schema: None,
+            version: String::new(),
+            preamble: None,
+            content: Some(TyXDocumentContent {
+                root: TyXNode::Root {
+                    children,
+                    direction: Some(TyXDirection(Some(TyXDirectionValue::Rtl))),
+                },
+            }),
+            settings: None,
+            filename: Some("/notes/Lecture.tyx".into()),
+            dirty: None,
+            id: None,
+        }
+    }
+
+    fn text(text: &str, format: TextFormat) -> TyXNode {
+        TyXNode::Text {
+            format,
+            style: None,
+            text: text.into(),
+        }
+    }
+
+    #[test]
+    fn test_page() {
+        let html = super::tyx_to_html(&document(vec![TyXNode::Paragraph {
+            children: vec![text(
+                "a < b",
+                TextFormat::BOLD | TextFormat::HIGHLIGHT | TextFormat::UPPERCASE,
+            )],
+            direction: None,
+            format: TyXNodeFormat::Center,
+        }]));
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>Lecture</title>"));
+        assert!(html.contains("<body dir=\"rtl\">"));
+        assert!(html.contains(
+            "<p style=\"text-align: center\"><span style=\"text-transform: uppercase\"><strong><mark>a &lt; b</mark></strong></span></p>"
+        ));
+    }
+
+    #[test]
+    fn test_nested_list_with_start() {
+        let item = |children| TyXNode::Listitem {
+            checked: None,
+            children,
+            value: 0,
+        };
+        let list = |list_type, start, children| TyXNode::List {
+            children,
+            direction: None,
+            list_type,
+            start,
+        };
+        let html = super::tyx_to_html(&document(vec![list(
+            TyXNodeListType::Number,
+            3,
+            vec![
+                item(vec![text("a", TextFormat::empty())]),
+                item(vec![list(
+                    TyXNodeListType::Bullet,
+                    1,
+                    vec![item(vec![text("b", TextFormat::empty())])],
+                )]),
+                item(vec![text("c", TextFormat::empty())]),
+            ],
+        )]));
+        assert!(html.contains("<ol start=\"3\"><li>a<ul><li>b</li></ul></li><li>c</li></ol>"));
+    }
+
+    #[test]
+    fn test_math_and_footnote() {
+        let html = super::tyx_to_html(&document(vec![TyXNode::Paragraph {
+            children: vec![
+                TyXNode::Math {
+                    formula: Some("x^2".into()),
+                    inline: Some(true),
+                    typst: Some("x^2".into()),
+                },
+                TyXNode::Functioncall {
+                    name: Some("footnote".into()),
+                    named_parameters: Default::default(),
+                    position_parameters: vec![tyx_schema::TyXValue::Content {
+                        value: Some(TyXNode::Root {
+                            children: vec![text("note", TextFormat::empty())],
+                            direction: None,
+                        }),
+                    }],
+                },
+            ],
+            direction: None,
+            format: TyXNodeFormat::X,
+        }]));
+        assert!(html.contains("<span class=\"math inline\">\\(x^2\\)</span>"));
+        assert!(html.contains("<a href=\"#footnote-1\">1</a>"));
+        assert!(html.contains("<li id=\"footnote-1\">note"));
+    }
+}
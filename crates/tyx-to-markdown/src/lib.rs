@@ -0,0 +1,432 @@
+//! Converts a TyX document to a GitHub Flavored Markdown string.
+//!
+//! Markdown can't express everything TyX can, so the conversion is lossy:
+//! - directions, paragraph alignments and text styles are dropped,
+//! - underlined, subscript and superscript texts are kept as plain text,
+//! - math is written as `$...$` (or `$$...$$` for blocks) using its LaTeX
+//!   formula, falling back to its Typst code,
+//! - Typst code and function calls are kept as inline code,
+//! - the first table row becomes the table's header.
+use cmark_writer::{
+    CommonMarkWriter,
+    ast::{CodeBlockType, HeadingType, ListItem, Node, TableAlignment, TaskListStatus},
+};
+use tyx_schema::{TextFormat, TyXDocument, TyXNode, TyXNodeListType, TyXNodeTag};
+use tyx_to_typst::{
+    ConversionError, try_parse_tyx_document, try_stringify_function, try_validate_tyx_document,
+};
+
+/// Returns the Markdown heading level of the given tag.
+fn heading_level(tag: &TyXNodeTag) -> u8 {
+    match tag {
+        TyXNodeTag::H1 => 1,
+        TyXNodeTag::H2 => 2,
+        TyXNodeTag::H3 => 3,
+        TyXNodeTag::H4 => 4,
+        TyXNodeTag::H5 => 5,
+        TyXNodeTag::H6 => 6,
+    }
+}
+
+/// Returns the text of a node, used for code blocks and Typst code.
+fn node_to_text(node: &TyXNode) -> String {
+    match node {
+        TyXNode::Text { text, .. } | TyXNode::CodeHighlight { text } | TyXNode::Tab { text } => {
+            text.clone()
+        }
+        TyXNode::Linebreak => "\n".into(),
+        TyXNode::Root { children, .. } | TyXNode::Code { children, .. } => {
+            children.iter().map(node_to_text).collect()
+        }
+        TyXNode::Paragraph { children, .. } => {
+            children.iter().map(node_to_text).collect::<String>() + "\n"
+        }
+        _ => String::new(),
+    }
+}
+
+/// Applies the TyX text format to a Markdown text node.
+fn apply_text_format(text: &str, format: TextFormat) -> Node {
+    let mut result = if format.contains(TextFormat::CODE) {
+        Node::InlineCode(text.into())
+    } else {
+        Node::Text(text.into())
+    };
+    if format.contains(TextFormat::STRIKETHROUGH) {
+        result = Node::Strikethrough(vec![result]);
+    }
+    if format.contains(TextFormat::ITALIC) {
+        result = Node::Emphasis(vec![result]);
+    }
+    if format.contains(TextFormat::BOLD) {
+        result = Node::Strong(vec![result]);
+    }
+    result
+}
+
+/// Converts an inline TyX node to Markdown, or returns `None` if it is a block.
+fn inline_to_markdown(node: &TyXNode) -> Result<Option<Node>, ConversionError> {
+    let result = match node {
+        TyXNode::Text { text, format, .. } => apply_text_format(text, *format),
+        TyXNode::CodeHighlight { text } | TyXNode::Tab { text } => Node::Text(text.into()),
+        TyXNode::Linebreak => Node::HardBreak,
+        TyXNode::Math {
+            formula,
+            inline,
+            typst,
+        } => {
+            let math = formula
+                .as_ref()
+                .or(typst.as_ref())
+                .cloned()
+                .unwrap_or_default();
+            let delimiter = if inline.unwrap_or(false) { "$" } else { "$$" };
+            Node::Text(format!("{delimiter}{}{delimiter}", math.trim()).into())
+        }
+        TyXNode::Link { children, url } => Node::Link {
+            url: url.into(),
+            title: None,
+            content: inlines_to_markdown(children)?,
+        },
+        TyXNode::Image { src } => Node::Image {
+            url: src.into(),
+            title: None,
+            alt: Vec::new(),
+        },
+        TyXNode::Typstcode { text } => {
+            Node::InlineCode(node_to_text(&text.editor_state.root).trim_end().into())
+        }
+        TyXNode::Functioncall {
+            name,
+            named_parameters,
+            position_parameters,
+        } => Node::InlineCode(
+            try_stringify_function(name, position_parameters, named_parameters, true)?.into(),
+        ),
+        _ => return Ok(None),
+    };
+    Ok(Some(result))
+}
+
+/// Converts inline TyX nodes to Markdown, flattening any nested blocks.
+fn inlines_to_markdown(children: &[TyXNode]) -> Result<Vec<Node>, ConversionError> {
+    let mut result = Vec::new();
+    for child in children {
+        if let Some(node) = inline_to_markdown(child)? {
+            result.push(node);
+            continue;
+        }
+        if !result.is_empty() {
+            result.push(Node::Text(" ".into()));
+        }
+        for block in blocks_to_markdown(std::slice::from_ref(child))? {
+            match block {
+                Node::Paragraph(nodes) => result.extend(nodes),
+                block => result.push(block),
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Converts the items of a TyX list to Markdown list items.
+///
+/// In TyX, a nested list is held by an item of its own following its parent
+/// item, while in Markdown it is part of its parent item.
+fn list_items_to_markdown(
+    children: &[TyXNode],
+    list_type: &TyXNodeListType,
+) -> Result<Vec<ListItem>, ConversionError> {
+    let mut items: Vec<ListItem> = Vec::new();
+    for child in children {
+        let TyXNode::Listitem {
+            checked,
+            children,
+            value,
+        } = child
+        else {
+            continue;
+        };
+        if let [nested @ TyXNode::List { .. }] = children.as_slice()
+            && let Some(
+                ListItem::Unordered { content }
+                | ListItem::Ordered { content, .. }
+                | ListItem::Task { content, .. },
+            ) = items.last_mut()
+        {
+            content.extend(blocks_to_markdown(std::slice::from_ref(nested))?);
+            continue;
+        }
+
+        let content = blocks_to_markdown(children)?;
+        items.push(match list_type {
+            TyXNodeListType::Bullet => ListItem::Unordered { content },
+            TyXNodeListType::Number => ListItem::Ordered {
+                number: u32::try_from(*value).ok(),
+                content,
+            },
+            TyXNodeListType::Check => ListItem::Task {
+                status: if *checked == Some(true) {
+                    TaskListStatus::Checked
+                } else {
+                    TaskListStatus::Unchecked
+                },
+                content,
+            },
+        });
+    }
+    Ok(items)
+}
+
+/// Writes a Markdown node to a string.
+fn write(node: &Node) -> Result<String, ConversionError> {
+    let mut writer = CommonMarkWriter::new();
+    writer
+        .write(node)
+        .map_err(|err| ConversionError::InvalidValue {
+            path: "root".into(),
+            message: err.to_string(),
+        })?;
+    Ok(writer.into_string().to_string())
+}
+
+/// Converts the cells of a table row to Markdown.
+///
+/// Table cells can only hold a single line, so each cell is written on its own
+/// with its line breaks replaced by `<br>`.
+fn row_to_markdown(row: &TyXNode, columns: usize) -> Result<Vec<Node>, ConversionError> {
+    let mut cells = Vec::new();
+    if let TyXNode::Tablerow { children } = row {
+        for cell in children {
+            let TyXNode::Tablecell { children, .. } = cell else {
+                continue;
+            };
+            let markdown = write(&Node::Paragraph(inlines_to_markdown(children)?))?;
+            let lines: Vec<_> = markdown
+                .trim()
+                .lines()
+                .map(|line| line.trim_end_matches(['\\', ' ']).replace('|', "\\|"))
+                .collect();
+            cells.push(Node::Text(lines.join("<br>").into()));
+        }
+    }
+    cells.resize_with(columns, || Node::Text("".into()));
+    Ok(cells)
+}
+
+/// Converts TyX nodes to Markdown blocks, gathering inline nodes into
+/// paragraphs.
+fn blocks_to_markdown(children: &[TyXNode]) -> Result<Vec<Node>, ConversionError> {
+    let mut result = Vec::new();
+    let mut inlines = Vec::new();
+    for child in children {
+        if let Some(node) = inline_to_markdown(child)? {
+            inlines.push(node);
+            continue;
+        }
+        if !inlines.is_empty() {
+            result.push(Node::Paragraph(std::mem::take(&mut inlines)));
+        }
+
+        match child {
+            TyXNode::Root { children, .. }
+            | TyXNode::Paragraph { children, .. }
+            | TyXNode::Listitem { children, .. }
+            | TyXNode::Tablerow { children }
+            | TyXNode::Tablecell { children, .. } => {
+                result.extend(blocks_to_markdown(children)?);
+            }
+            TyXNode::Heading { children, tag } => result.push(Node::Heading {
+                level: heading_level(tag),
+                content: inlines_to_markdown(children)?,
+                heading_type: HeadingType::Atx,
+            }),
+            TyXNode::Quote { children, .. } => {
+                result.push(Node::BlockQuote(blocks_to_markdown(children)?));
+            }
+            TyXNode::Code { language, .. } => result.push(Node::CodeBlock {
+                language: language.as_deref().map(Into::into),
+                content: node_to_text(child).into(),
+                block_type: CodeBlockType::Fenced,
+            }),
+            TyXNode::List {
+                children,
+                list_type,
+                start,
+                ..
+            } => {
+                let items = list_items_to_markdown(children, list_type)?;
+                result.push(match list_type {
+                    TyXNodeListType::Number => Node::OrderedList {
+                        start: u32::try_from(*start).unwrap_or(1),
+                        items,
+                    },
+                    TyXNodeListType::Bullet | TyXNodeListType::Check => Node::UnorderedList(items),
+                });
+            }
+            TyXNode::Table { children, .. } => {
+                let Some((header, rows)) = children.split_first() else {
+                    continue;
+                };
+                let columns = match header {
+                    TyXNode::Tablerow { children } => children.len(),
+                    _ => 0,
+                };
+                result.push(Node::Table {
+                    headers: row_to_markdown(header, columns)?,
+                    alignments: vec![TableAlignment::None; columns],
+                    rows: rows
+                        .iter()
+                        .map(|row| row_to_markdown(row, columns))
+                        .collect::<Result<_, _>>()?,
+                });
+            }
+            TyXNode::Horizontalrule => result.push(Node::ThematicBreak),
+            _ => {}
+        }
+    }
+    if !inlines.is_empty() {
+        result.push(Node::Paragraph(inlines));
+    }
+    Ok(result)
+}
+
+/// Converts a TyX document to Markdown, failing if the document is invalid, a
+/// function call can't be converted or the Markdown can't be written, e.g. if a
+/// heading contains a line break.
+pub fn try_tyx_to_markdown(document: &TyXDocument) -> Result<String, ConversionError> {
+    try_validate_tyx_document(document)?;
+    let content = match &document.content {
+        Some(content) => blocks_to_markdown(std::slice::from_ref(&content.root))?,
+        None => Vec::new(),
+    };
+    write(&Node::Document(content))
+}
+
+/// Converts the serialized TyX document to Markdown, failing if the document
+/// is malformed or can't be converted.
+pub fn try_serialized_tyx_to_markdown(document: &str) -> Result<String, ConversionError> {
+    let document = try_parse_tyx_document(document)?;
+    try_tyx_to_markdown(&document)
+}
+
+#[cfg(test)]
+mod tests {
+    use tyx_schema::{
+        TextFormat, TyXDocument, TyXDocumentContent, TyXNode, TyXNodeFormat, TyXNodeListType,
+    };
+
+    fn document(children: Vec<TyXNode>) -> TyXDocument {
+        TyXDocument {
+            schema: None,
+            version: String::new(),
+            preamble: None,
+            content: Some(TyXDocumentContent {
+                root: TyXNode::Root {
+                    children,
+                    direction: None,
+                },
+            }),
+            settings: None,
+            filename: None,
+            dirty: None,
+            id: None,
+        }
+    }
+
+    fn paragraph(children: Vec<TyXNode>) -> TyXNode {
+        TyXNode::Paragraph {
+            children,
+            direction: None,
+            format: TyXNodeFormat::X,
+        }
+    }
+
+    fn text(text: &str, format: TextFormat) -> TyXNode {
+        TyXNode::Text {
+            format,
+            style: None,
+            text: text.into(),
+        }
+    }
+
+    #[test]
+    fn test_formatted_text_and_math() {
+        let markdown = super::try_tyx_to_markdown(&document(vec![paragraph(vec![
+            text("bold", TextFormat::BOLD),
+            text(" and ", TextFormat::empty()),
+            text("code", TextFormat::CODE),
+            text(" ", TextFormat::empty()),
+            TyXNode::Math {
+                formula: Some("x^2".into()),
+                inline: Some(true),
+                typst: Some("x^2".into()),
+            },
+        ])]))
+        .unwrap();
+        assert!(markdown.contains("**bold**"), "{markdown}");
+        assert!(markdown.contains("`code`"), "{markdown}");
+        assert!(markdown.contains("$x^2$"), "{markdown}");
+    }
+
+    #[test]
+    fn test_code_block_and_task_list() {
+        let markdown = super::try_tyx_to_markdown(&document(vec![
+            TyXNode::Code {
+                children: vec![
+                    TyXNode::CodeHighlight { text: "fn".into() },
+                    TyXNode::Linebreak,
+                ],
+                language: Some("rust".into()),
+            },
+            TyXNode::List {
+                children: vec![
+                    TyXNode::Listitem {
+                        checked: Some(false),
+                        children: vec![text("task", TextFormat::empty())],
+                        value: 1,
+                    },
+                    TyXNode::Listitem {
+                        checked: Some(true),
+                        children: vec![text("done", TextFormat::empty())],
+                        value: 2,
+                    },
+                ],
+                direction: None,
+                list_type: TyXNodeListType::Check,
+                start: 1,
+            },
+        ]))
+        .unwrap();
+        assert!(markdown.contains("```rust\nfn\n"), "{markdown}");
+        assert!(markdown.contains("[ ] task"), "{markdown}");
+        assert!(markdown.contains("[x] done"), "{markdown}");
+    }
+
+    #[test]
+    fn test_table_header() {
+        let cell = |content: &str| TyXNode::Tablecell {
+            children: vec![paragraph(vec![text(content, TextFormat::empty())])],
+            direction: None,
+        };
+        let markdown = super::try_tyx_to_markdown(&document(vec![TyXNode::Table {
+            children: vec![
+                TyXNode::Tablerow {
+                    children: vec![cell("a"), cell("b")],
+                },
+                TyXNode::Tablerow {
+                    children: vec![cell("c")],
+                },
+            ],
+            direction: None,
+        }]))
+        .unwrap();
+        let lines: Vec<_> = markdown.lines().collect();
+        assert!(
+            lines[0].contains('a') && lines[0].contains('b'),
+            "{markdown}"
+        );
+        assert!(lines[1].contains("---"), "{markdown}");
+        assert!(lines[2].contains('c'), "{markdown}");
+    }
+}
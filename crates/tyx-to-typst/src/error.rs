@@ -0,0 +1,93 @@
+//! Errors raised while converting TyX to Typst.
+use std::fmt;
+
+/// An error raised while converting a TyX document to Typst.
+///
+/// Every variant carries the path of the offending element, e.g.
+/// `root/children/3/children/1` for nodes, or the JSON path reported by the
+/// deserializer for parse errors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConversionError {
+    /// The serialized input couldn't be deserialized.
+    Parse {
+        /// The JSON path at which deserialization failed.
+        path: String,
+        /// The deserializer's message.
+        message: String,
+    },
+    /// A node which has no Typst representation.
+    UnsupportedNode {
+        /// The path of the node.
+        path: String,
+        /// A short description of the node.
+        node: String,
+    },
+    /// A node or value whose contents can't be converted.
+    InvalidValue {
+        /// The path of the node or value.
+        path: String,
+        /// Why the value is invalid.
+        message: String,
+    },
+}
+
+impl ConversionError {
+    /// Creates an error from a failed deserialization.
+    pub(crate) fn parse(error: serde_path_to_error::Error<serde_json::Error>) -> Self {
+        Self::Parse {
+            path: error.path().to_string(),
+            message: error.into_inner().to_string(),
+        }
+    }
+
+    /// Creates an error from a failed migration.
+    pub(crate) fn migration(error: tyx_schema::MigrationError) -> Self {
+        let path = match error {
+            tyx_schema::MigrationError::NotAnObject => "",
+            _ => "version",
+        };
+        Self::Parse {
+            path: path.into(),
+            message: error.to_string(),
+        }
+    }
+
+    /// Returns the path of the element that caused the error.
+    pub fn path(&self) -> &str {
+        match self {
+            Self::Parse { path, .. }
+            | Self::UnsupportedNode { path, .. }
+            | Self::InvalidValue { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parse { path, message } if path.is_empty() || path == "." => {
+                write!(f, "failed to parse TyX: {message}")
+            }
+            Self::Parse { path, message } => {
+                write!(f, "failed to parse TyX at `{path}`: {message}")
+            }
+            Self::UnsupportedNode { path, node } => {
+                write!(f, "unsupported node at `{path}`: {node}")
+            }
+            Self::InvalidValue { path, message } => {
+                write!(f, "invalid value at `{path}`: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+impl From<tyx_schema::Issue> for ConversionError {
+    fn from(issue: tyx_schema::Issue) -> Self {
+        Self::InvalidValue {
+            path: issue.path,
+            message: issue.message,
+        }
+    }
+}
@@ -4,6 +4,13 @@ use std::{collections::HashMap, sync::LazyLock};
 use regex::{Captures, Regex};
 use tyx_schema::*;
 
+mod error;
+mod source_map;
+
+pub use error::ConversionError;
+use source_map::Fragment;
+pub use source_map::{SourceMap, SourceMapping};
+
 fn get_tag_number(tag: &TyXNodeTag) -> i64 {
     match tag {
         TyXNodeTag::H1 => 1,
@@ -15,6 +22,15 @@ fn get_tag_number(tag: &TyXNodeTag) -> i64 {
     }
 }
 
+/// Appends a segment to a node path, e.g. `root` and `children` to `root/children`.
+fn join_path(path: &str, segment: impl std::fmt::Display) -> String {
+    if path.is_empty() {
+        segment.to_string()
+    } else {
+        format!("{path}/{segment}")
+    }
+}
+
 /// Converts TyX nodes to text by joining their texts together.
 fn nodes_to_text(nodes: &[TyXNode]) -> String {
     nodes
@@ -26,121 +42,175 @@ fn nodes_to_text(nodes: &[TyXNode]) -> String {
 
 /// Converts a TyX node to text.
 fn node_to_text(node: &TyXNode) -> String {
-    let result = match node {
-        TyXNode::Root { children, .. }
-        | TyXNode::Paragraph { children, .. }
-        | TyXNode::Listitem { children, .. }
-        | TyXNode::List { children, .. }
-        | TyXNode::Code { children, .. }
-        | TyXNode::Quote { children, .. }
-        | TyXNode::Table { children, .. }
-        | TyXNode::Tablerow { children, .. }
-        | TyXNode::Tablecell { children, .. }
-        | TyXNode::Link { children, .. }
-        | TyXNode::Heading { children, .. } => Some(nodes_to_text(children)),
-        TyXNode::Text { text, .. } => Some(text.clone()),
-        TyXNode::Math { typst, .. } => typst.clone(),
-        TyXNode::Linebreak => Some("\n".into()),
-        TyXNode::Typstcode { text, .. } => Some(node_to_text(&text.editor_state.root)),
-        _ => None,
-    };
-
-    result.unwrap_or("".into())
+    match node {
+        TyXNode::Text { text, .. }
+        | TyXNode::CodeHighlight { text, .. }
+        | TyXNode::Tab { text, .. } => text.clone(),
+        TyXNode::Math { typst, .. } => typst.clone().unwrap_or_default(),
+        TyXNode::Linebreak => "\n".into(),
+        TyXNode::Typstcode { text, .. } => node_to_text(&text.editor_state.root),
+        _ => nodes_to_text(node.children()),
+    }
 }
 
 /// Applies the given direction to the output Typst code.
-fn apply_direction(result: &str, direction: TyXDirection) -> String {
+fn apply_direction(result: Fragment, direction: TyXDirection) -> Fragment {
     match direction.0 {
-        Some(d) => format!("#text(dir: {d})[{result}]"),
-        None => result.into(),
+        Some(d) => result.wrap(&format!("#text(dir: {d})["), "]"),
+        None => result,
     }
 }
 
 /// Applies the given text format to the output Typst code.
-fn apply_text_format(mut result: String, text: &str, format: i64) -> String {
-    if format & (TextFormat::Bold as i64) != 0 {
-        result = format!("#strong[{result}]");
+///
+/// Typst can't capitalize words, so capitalized text is set in small capitals
+/// instead.
+fn apply_text_format(mut result: String, text: &str, format: TextFormat) -> String {
+    if format.contains(TextFormat::CODE) {
+        return format!("#raw({})", serde_json::to_string(text).unwrap());
+    }
+
+    for (flag, function) in [
+        (TextFormat::BOLD, "strong"),
+        (TextFormat::ITALIC, "emph"),
+        (TextFormat::UNDERLINE, "underline"),
+        (TextFormat::STRIKETHROUGH, "strike"),
+        (TextFormat::SUBSCRIPT, "sub"),
+        (TextFormat::SUPERSCRIPT, "super"),
+        (TextFormat::HIGHLIGHT, "highlight"),
+        (TextFormat::LOWERCASE, "lower"),
+        (TextFormat::UPPERCASE, "upper"),
+        (TextFormat::CAPITALIZE, "smallcaps"),
+    ] {
+        if format.contains(flag) {
+            result = format!("#{function}[{result}]");
+        }
     }
-    if format & (TextFormat::Italic as i64) != 0 {
-        result = format!("#emph[{result}]");
+
+    result
+}
+
+/// The generic CSS font families, which Typst has no equivalent of.
+const GENERIC_FONT_FAMILIES: &[&str] = &[
+    "serif",
+    "sans-serif",
+    "monospace",
+    "cursive",
+    "fantasy",
+    "system-ui",
+    "math",
+    "emoji",
+];
+
+/// Converts a CSS font size to a Typst length.
+///
+/// Typst has no root font size, so `rem` is relative to the surrounding text
+/// like `em`.
+fn font_size_to_typst(size: FontSize) -> String {
+    match size.unit {
+        FontSizeUnit::Pt => format!("{}pt", size.value),
+        FontSizeUnit::Px => format!("{}pt", size.value * 0.75),
+        FontSizeUnit::Em | FontSizeUnit::Rem => format!("{}em", size.value),
+        FontSizeUnit::Percent => format!("{}em", size.value / 100.0),
     }
-    if format & (TextFormat::Underline as i64) != 0 {
-        result = format!("#underline[{result}]");
+}
+
+/// Applies the given inline CSS style to the output Typst code, ignoring what
+/// Typst can't express, which [`validate`] warns about.
+fn apply_text_style(mut result: String, style: &str) -> String {
+    let (style, _) = TextStyle::parse(style);
+    if let Some(color) = style.background_color {
+        result = format!("#highlight(fill: rgb(\"{color}\"))[{result}]");
     }
-    if format & (TextFormat::Strikethrough as i64) != 0 {
-        result = format!("#strike[{result}]");
+
+    let mut arguments = Vec::new();
+    if let Some(color) = style.color {
+        arguments.push(format!("fill: rgb(\"{color}\")"));
     }
-    if format & (TextFormat::Subscript as i64) != 0 {
-        result = format!("#sub[{result}]");
+    let fonts = style
+        .font_families
+        .iter()
+        .filter(|family| !GENERIC_FONT_FAMILIES.contains(&family.to_ascii_lowercase().as_str()))
+        .map(|family| serde_json::to_string(family).unwrap())
+        .collect::<Vec<_>>();
+    match fonts.as_slice() {
+        [] => {}
+        [font] => arguments.push(format!("font: {font}")),
+        fonts => arguments.push(format!("font: ({})", fonts.join(", "))),
     }
-    if format & (TextFormat::Superscript as i64) != 0 {
-        result = format!("#super[{result}]");
+    if let Some(size) = style.font_size {
+        arguments.push(format!("size: {}", font_size_to_typst(size)));
     }
-    if format & (TextFormat::Code as i64) != 0 {
-        result = format!("#raw({})", serde_json::to_string(text).unwrap());
+    if !arguments.is_empty() {
+        result = format!("#text({})[{result}]", arguments.join(", "));
     }
 
     result
 }
 
-/// Extracts a `font-size` pt value from a CSS inline style string.
-/// Returns the numeric part if the value uses `pt` units.
-fn parse_font_size_pt(style: &str) -> Option<f64> {
-    for part in style.split(';') {
-        let part = part.trim();
-        if let Some(value) = part.strip_prefix("font-size:") {
-            let value = value.trim();
-            if let Some(n) = value.strip_suffix("pt") {
-                return n.trim().parse::<f64>().ok();
-            }
-        }
-    }
-    None
-}
-
 /// Applies the given alignment format to the output Typst code.
-fn apply_format(result: &str, format: &str) -> String {
+fn apply_format(result: Fragment, format: &str) -> Fragment {
     if format == "justify" {
-        format!("#par(justify: true)[{result}]")
+        result.wrap("#par(justify: true)[", "]")
     } else if format == "left"
         || format == "center"
         || format == "right"
         || format == "start"
         || format == "end"
     {
-        format!("#align({format})[{result}]")
+        result.wrap(&format!("#align({format})["), "]")
     } else {
-        result.into()
+        result
     }
 }
 
 /// Joins the children's Typst code, and adds newlines between paragraph children.
-fn children_to_typst(children: &[TyXNode]) -> String {
-    let mut result = String::new();
-    let translated = children.iter().map(node_to_typst).collect::<Vec<_>>();
-    for i in 0..translated.len() {
-        result += &translated[i].clone().unwrap_or_default();
-        if let TyXNode::Paragraph { .. } = children[i]
-            && i != translated.len() - 1
+/// The children are located at `{path}/children/{index}`.
+fn children_to_typst(
+    children: &[TyXNode],
+    path: &str,
+    lenient: bool,
+) -> Result<Fragment, ConversionError> {
+    let mut result = Fragment::default();
+    for (i, child) in children.iter().enumerate() {
+        result.push(node_to_typst(
+            child,
+            &join_path(path, format!("children/{i}")),
+            lenient,
+        )?);
+        if let TyXNode::Paragraph { .. } = child
+            && i != children.len() - 1
         {
-            result += "\n\n"
+            result.push_str("\n\n");
         }
     }
-    result
+    Ok(result)
 }
 
 /// Escapes special Typst symbols.
 fn typst_escape(text: &str) -> String {
     static TYPST_SPECIAL_SYMBOL_REGEX: LazyLock<Regex> =
-        LazyLock::new(|| Regex::new("[#=\\[\\]$*_`@<\\-+/\\'\\\"~]").unwrap());
+        LazyLock::new(|| Regex::new("[\\\\#=\\[\\]$*_`@<\\-+/\\'\\\"~]").unwrap());
     TYPST_SPECIAL_SYMBOL_REGEX
         .replace_all(text, |c: &Captures| String::from("\\") + &c[0])
         .to_string()
 }
 
-/// Converts a TyX node to Typst code.
-fn node_to_typst(root: &TyXNode) -> Option<String> {
-    match root {
+/// Converts a TyX node at the given path to Typst code.
+///
+/// When lenient, a node which can't be converted is left out instead of
+/// failing, as the infallible API always did.
+fn node_to_typst(root: &TyXNode, path: &str, lenient: bool) -> Result<Fragment, ConversionError> {
+    match convert_node(root, path, lenient) {
+        Err(_) if lenient => Ok(Fragment::default()),
+        result => result,
+    }
+}
+
+/// Converts a TyX node at the given path to Typst code, failing if the node
+/// itself can't be converted, see [`node_to_typst`].
+fn convert_node(root: &TyXNode, path: &str, lenient: bool) -> Result<Fragment, ConversionError> {
+    let result = match root {
         TyXNode::Root {
             children,
             direction,
@@ -150,46 +220,53 @@ fn node_to_typst(root: &TyXNode) -> Option<String> {
             children,
             direction,
             ..
-        } => Some(apply_direction(
-            &children_to_typst(children),
+        } => apply_direction(
+            children_to_typst(children, path, lenient)?,
             direction.clone().unwrap_or(TyXDirection(None)),
-        )),
+        ),
         TyXNode::Quote {
             children,
             direction,
             ..
-        } => Some(apply_direction(
-            &format!("#quote(block: true)[{}]", children_to_typst(children)),
+        } => apply_direction(
+            children_to_typst(children, path, lenient)?.wrap("#quote(block: true)[", "]"),
             direction.clone().unwrap_or(TyXDirection(None)),
-        )),
+        ),
         TyXNode::Paragraph {
             children,
             direction,
             format,
             ..
-        } => Some(apply_direction(
-            &apply_format(&children_to_typst(children), &format!("{format}")),
+        } => apply_direction(
+            apply_format(
+                children_to_typst(children, path, lenient)?,
+                &format!("{format}"),
+            ),
             direction.clone().unwrap_or(TyXDirection(None)),
-        )),
+        ),
         TyXNode::Text {
-            text, format, style, ..
+            text,
+            format,
+            style,
+            ..
         } => {
-            let mut result = apply_text_format(typst_escape(text), text, *format);
-            if let Some(size) = style.as_deref().and_then(parse_font_size_pt) {
-                result = format!("#text(size: {size}pt)[{result}]");
+            let result = apply_text_format(typst_escape(text), text, *format);
+            match style {
+                Some(style) => apply_text_style(result, style),
+                None => result,
             }
-            Some(result)
+            .into()
         }
-        TyXNode::Tab { .. } => Some("\t".into()),
-        TyXNode::CodeHighlight { text, .. } => Some(typst_escape(text)),
+        TyXNode::Tab { .. } => "\t".into(),
+        TyXNode::CodeHighlight { text, .. } => typst_escape(text).into(),
         TyXNode::Math { typst, inline, .. } => {
             if inline.unwrap_or(false) {
-                Some(format!("${}$", typst.clone().unwrap_or_default()))
+                format!("${}$", typst.clone().unwrap_or_default()).into()
             } else {
-                Some(format!("$ {} $\n", typst.clone().unwrap_or_default()))
+                format!("$ {} $\n", typst.clone().unwrap_or_default()).into()
             }
         }
-        TyXNode::Listitem { children, .. } => Some(children_to_typst(children)),
+        TyXNode::Listitem { children, .. } => children_to_typst(children, path, lenient)?,
         TyXNode::List {
             children,
             direction,
@@ -197,44 +274,69 @@ fn node_to_typst(root: &TyXNode) -> Option<String> {
             start,
             ..
         } => {
-            let mut result = match list_type {
+            let mut result = Fragment::from(match list_type {
                 TyXNodeListType::Bullet => "\n#list(".into(),
                 TyXNodeListType::Number => format!("\n#enum(start: {start},"),
-                _ => return None,
-            };
+                _ => {
+                    return Err(ConversionError::UnsupportedNode {
+                        path: path.into(),
+                        node: format!("list of type `{list_type}`"),
+                    });
+                }
+            });
+            if children.is_empty() {
+                return Err(ConversionError::InvalidValue {
+                    path: path.into(),
+                    message: "a list must have at least one item".into(),
+                });
+            }
 
             for i in 0..(children.len() - 1) {
-                result += &format!("[{}]", node_to_typst(&children[i]).unwrap_or_default());
+                result.push(
+                    node_to_typst(
+                        &children[i],
+                        &join_path(path, format!("children/{i}")),
+                        lenient,
+                    )?
+                    .wrap("[", "]"),
+                );
 
                 let delimiter = if let TyXNode::Listitem {
                     children: item_children,
                     ..
                 } = &children[i + 1]
-                    && let TyXNode::List { .. } = item_children[0]
+                    && let Some(TyXNode::List { .. }) = item_children.first()
                 {
                     " + "
                 } else {
                     ", "
                 };
-                result += delimiter;
+                result.push_str(delimiter);
             }
 
             if let Some(last) = children.last() {
-                result += &format!("[{}]", node_to_typst(last).unwrap_or_default());
+                result.push(
+                    node_to_typst(
+                        last,
+                        &join_path(path, format!("children/{}", children.len() - 1)),
+                        lenient,
+                    )?
+                    .wrap("[", "]"),
+                );
             }
 
-            result += ")\n";
+            result.push_str(")\n");
 
-            Some(apply_direction(
-                &result,
-                direction.clone().unwrap_or(TyXDirection(None)),
-            ))
+            apply_direction(result, direction.clone().unwrap_or(TyXDirection(None)))
         }
-        TyXNode::Code { language, .. } => Some(format!(
+        TyXNode::Code { language, .. } => format!(
             "#text(dir: ltr)[#raw(block: true, lang: {}, {})]",
-            serde_json::to_string(&language.clone().unwrap_or("none".into())).unwrap(),
+            language.as_ref().map_or(String::from("none"), |language| {
+                serde_json::to_string(language).unwrap()
+            }),
             serde_json::to_string(&node_to_text(root)).unwrap()
-        )),
+        )
+        .into(),
         TyXNode::Table {
             children,
             direction,
@@ -244,110 +346,192 @@ fn node_to_typst(root: &TyXNode) -> Option<String> {
                 children: row_children,
             }) = children.first()
             else {
-                return None;
+                return Err(ConversionError::InvalidValue {
+                    path: path.into(),
+                    message: "a table must start with a table row".into(),
+                });
             };
             let columns = (0..row_children.len())
                 .map(|_| String::from("1fr"))
                 .collect::<Vec<String>>()
                 .join(", ");
-            let children = children
-                .iter()
-                .map(|child| node_to_typst(child).unwrap_or_default())
-                .collect::<Vec<String>>()
-                .join(", ");
+            let mut result = Fragment::from(format!("#table(columns: ({columns}), "));
+            for (i, child) in children.iter().enumerate() {
+                if i != 0 {
+                    result.push_str(", ");
+                }
+                result.push(node_to_typst(
+                    child,
+                    &join_path(path, format!("children/{i}")),
+                    lenient,
+                )?);
+            }
+            result.push_str(")");
 
-            Some(apply_direction(
-                &format!("#table(columns: ({columns}), {children})"),
-                direction.clone().unwrap_or(TyXDirection(None)),
-            ))
+            apply_direction(result, direction.clone().unwrap_or(TyXDirection(None)))
         }
-        TyXNode::Tablerow { children, .. } => Some(
-            children
-                .iter()
-                .map(|child| format!("[{}]", node_to_typst(child).unwrap_or_default()))
-                .collect::<Vec<String>>()
-                .join(", "),
-        ),
-        TyXNode::Linebreak => Some("\\ \n".into()),
-        TyXNode::Horizontalrule => Some("#line(length: 100%)\n".into()),
-        TyXNode::Typstcode { text, .. } => Some(node_to_text(&text.editor_state.root)),
+        TyXNode::Tablerow { children, .. } => {
+            let mut result = Fragment::default();
+            for (i, child) in children.iter().enumerate() {
+                if i != 0 {
+                    result.push_str(", ");
+                }
+                result.push(
+                    node_to_typst(child, &join_path(path, format!("children/{i}")), lenient)?
+                        .wrap("[", "]"),
+                );
+            }
+            result
+        }
+        TyXNode::Linebreak => "\\ \n".into(),
+        TyXNode::Horizontalrule => "#line(length: 100%)\n".into(),
+        TyXNode::Typstcode { text, .. } => node_to_text(&text.editor_state.root).into(),
         TyXNode::Image { src, .. } => {
-            Some(format!("#image({})", serde_json::to_string(src).unwrap()))
+            format!("#image({})", serde_json::to_string(src).unwrap()).into()
         }
-        TyXNode::Link { url, children, .. } => Some(format!(
-            "#link({})[{}]",
-            serde_json::to_string(url).unwrap(),
-            children_to_typst(children)
-        )),
-        TyXNode::Heading { tag, children, .. } => Some(format!(
-            "#heading(depth: {})[{}]",
-            get_tag_number(tag),
-            children_to_typst(children)
-        )),
+        TyXNode::Link { url, children, .. } => children_to_typst(children, path, lenient)?.wrap(
+            &format!("#link({})[", serde_json::to_string(url).unwrap()),
+            "]",
+        ),
+        TyXNode::Heading { tag, children, .. } => children_to_typst(children, path, lenient)?
+            .wrap(&format!("#heading(depth: {})[", get_tag_number(tag)), "]"),
         TyXNode::Functioncall {
             name,
             position_parameters,
             named_parameters,
             ..
-        } => Some(format!(
-            "#{}",
-            stringify_function(name, position_parameters, named_parameters, true)
-        )),
-    }
+        } => function_to_typst(
+            name,
+            position_parameters,
+            named_parameters,
+            true,
+            path,
+            lenient,
+        )?
+        .wrap("#", ""),
+    };
+
+    Ok(result.mapped(path))
 }
 
-/// Converts the given TyX function call to Typst code.
-pub fn stringify_function(
+/// Converts the given TyX function call at the given path to Typst code.
+fn function_to_typst(
     name: &Option<String>,
-    position_parameters: &Vec<TyXValue>,
+    position_parameters: &[TyXValue],
     named_parameters: &HashMap<String, TyXValue>,
     include_content: bool,
-) -> String {
+    path: &str,
+    lenient: bool,
+) -> Result<Fragment, ConversionError> {
     let mut parameters = Vec::new();
 
-    for parameter in position_parameters {
+    for (i, parameter) in position_parameters.iter().enumerate() {
         if !include_content && let TyXValue::Content { .. } = parameter {
             continue;
         }
 
-        if let Some(value) = tyx_value_to_typst(parameter.clone()) {
+        let parameter_path = join_path(path, format!("positionParameters/{i}"));
+        if let Some(value) = value_to_typst(parameter, &parameter_path, lenient)? {
             parameters.push(value);
         }
     }
     for (parameter_name, parameter_value) in named_parameters.iter() {
-        if let Some(value) = tyx_value_to_typst(parameter_value.clone()) {
-            parameters.push(format!("{parameter_name}: {value}"));
+        let parameter_path = join_path(path, format!("namedParameters/{parameter_name}"));
+        if let Some(value) = value_to_typst(parameter_value, &parameter_path, lenient)? {
+            parameters.push(value.wrap(&format!("{parameter_name}: "), ""));
+        }
+    }
+
+    let mut result = Fragment::from(format!("{}(", name.clone().unwrap_or_default()));
+    for (i, parameter) in parameters.into_iter().enumerate() {
+        if i != 0 {
+            result.push_str(", ");
         }
+        result.push(parameter);
     }
+    result.push_str(")");
+
+    Ok(result)
+}
 
-    format!(
-        "{}({})",
-        name.clone().unwrap_or_default(),
-        parameters.join(", ")
+/// Converts the given TyX function call to Typst code, leaving out the content
+/// parameters which can't be converted, see [`try_stringify_function`].
+#[allow(clippy::ptr_arg)]
+pub fn stringify_function(
+    name: &Option<String>,
+    position_parameters: &Vec<TyXValue>,
+    named_parameters: &HashMap<String, TyXValue>,
+    include_content: bool,
+) -> String {
+    function_to_typst(
+        name,
+        position_parameters,
+        named_parameters,
+        include_content,
+        "",
+        true,
     )
+    .map(|fragment| fragment.code)
+    .unwrap_or_default()
 }
 
-/// Converts a TyX value to a Typst string.
-pub fn tyx_value_to_typst(value: TyXValue) -> Option<String> {
-    match value {
-        TyXValue::Length { unit, value } => {
-            Some(unit.clone().map_or(String::from("none"), |unit| {
+/// Converts the given TyX function call to Typst code, failing if a content
+/// parameter can't be converted.
+pub fn try_stringify_function(
+    name: &Option<String>,
+    position_parameters: &[TyXValue],
+    named_parameters: &HashMap<String, TyXValue>,
+    include_content: bool,
+) -> Result<String, ConversionError> {
+    function_to_typst(
+        name,
+        position_parameters,
+        named_parameters,
+        include_content,
+        "",
+        false,
+    )
+    .map(|fragment| fragment.code)
+}
+
+/// Converts a TyX value at the given path to a Typst string.
+///
+/// When lenient, a content value which can't be converted is left out instead
+/// of failing.
+fn value_to_typst(
+    value: &TyXValue,
+    path: &str,
+    lenient: bool,
+) -> Result<Option<Fragment>, ConversionError> {
+    let result = match value {
+        TyXValue::Length { unit, value } => Some(Fragment::from(
+            unit.clone().map_or(String::from("none"), |unit| {
                 value.clone().unwrap_or("".into()) + unit.as_str()
-            }))
-        }
-        TyXValue::Boolean { value } => {
-            value.map(|v| if v { "true".into() } else { "false".into() })
-        }
-        TyXValue::Content { value } => {
-            if let Some(root) = value {
-                let converted = node_to_typst(&root);
+            }),
+        )),
+        TyXValue::Boolean { value } => value
+            .map(|v| if v { "true" } else { "false" })
+            .map(Fragment::from),
+        TyXValue::Content { value } => match value {
+            Some(root) => match convert_node(root, &join_path(path, "value"), lenient) {
+                Ok(fragment) => Some(fragment.wrap("[", "]")),
+                Err(_) if lenient => None,
+                Err(err) => return Err(err),
+            },
+            None => None,
+        },
+    };
 
-                converted.map(|c| String::from("[") + c.as_str() + "]")
-            } else {
-                None
-            }
-        }
-    }
+    Ok(result.map(|fragment| fragment.mapped(path)))
+}
+
+/// Converts a TyX value to a Typst string, or `None` if it is unset or is
+/// content which can't be converted.
+pub fn tyx_value_to_typst(value: TyXValue) -> Option<String> {
+    value_to_typst(&value, "", true)
+        .ok()
+        .flatten()
+        .map(|fragment| fragment.code)
 }
 
 /// Converts the given TyX document settings to Typst code.
@@ -358,6 +542,18 @@ fn tyx_document_settings_to_typst(settings: &Option<TyXDocumentSettings>) -> Str
         return String::new();
     };
 
+    if let Some(title) = &settings.title {
+        result += &format!(
+            "#set document(title: {})\n",
+            serde_json::to_string(title).unwrap()
+        );
+    }
+    if let Some(author) = &settings.author {
+        result += &format!(
+            "#set document(author: {})\n",
+            serde_json::to_string(author).unwrap()
+        );
+    }
     if let Some(paper) = &settings.paper {
         result += &format!(
             "#set page(paper: {})\n",
@@ -395,8 +591,52 @@ fn tyx_document_settings_to_typst(settings: &Option<TyXDocumentSettings>) -> Str
     result
 }
 
-/// Converts the given TyX document to Typst code.
+/// Converts the given TyX document to Typst code, leaving out the nodes which
+/// can't be converted, see [`try_tyx_to_typst`].
 pub fn tyx_to_typst(document: &TyXDocument) -> String {
+    document_to_typst(document, true)
+        .map(|fragment| fragment.code)
+        .unwrap_or_default()
+}
+
+/// Converts the given TyX document to Typst code, failing if the document is
+/// invalid, see [`validate`], or on the first node that can't be converted.
+pub fn try_tyx_to_typst(document: &TyXDocument) -> Result<String, ConversionError> {
+    document_to_typst(document, false).map(|fragment| fragment.code)
+}
+
+/// Converts the given TyX document to Typst code, along with a [`SourceMap`]
+/// from the generated code back to the document's nodes.
+pub fn try_tyx_to_typst_with_source_map(
+    document: &TyXDocument,
+) -> Result<(String, SourceMap), ConversionError> {
+    document_to_typst(document, false).map(|fragment| {
+        (
+            fragment.code,
+            SourceMap {
+                mappings: fragment.mappings,
+            },
+        )
+    })
+}
+
+/// Validates the TyX document, see [`validate`], failing on its first error
+/// and otherwise returning its warnings.
+pub fn try_validate_tyx_document(document: &TyXDocument) -> Result<Vec<Issue>, ConversionError> {
+    let (errors, warnings): (Vec<_>, Vec<_>) =
+        validate(document).into_iter().partition(Issue::is_error);
+    match errors.into_iter().next() {
+        Some(error) => Err(error.into()),
+        None => Ok(warnings),
+    }
+}
+
+/// Converts the given TyX document to a mapped Typst fragment, validating it
+/// first unless lenient, see [`node_to_typst`].
+fn document_to_typst(document: &TyXDocument, lenient: bool) -> Result<Fragment, ConversionError> {
+    if !lenient {
+        try_validate_tyx_document(document)?;
+    }
     let version = if document.version.is_empty() {
         "".into()
     } else {
@@ -407,41 +647,101 @@ pub fn tyx_to_typst(document: &TyXDocument) -> String {
     } else {
         "{}".into()
     };
-    let mut content = format!(
-        "// Automatically generated by TyX{version}.\n\n// Settings\n#metadata(json(bytes(```json {settings}```.text))) <tyx-settings>\n{}",
-        tyx_document_settings_to_typst(&document.settings)
+    let mut content = Fragment::from(format!(
+        "// Automatically generated by TyX{version}.\n\n// Settings\n"
+    ));
+    content.push(
+        Fragment::from(format!(
+            "#metadata(json(bytes(```json {settings}```.text))) <tyx-settings>\n{}",
+            tyx_document_settings_to_typst(&document.settings)
+        ))
+        .mapped("settings"),
     );
 
     if let Some(preamble) = &document.preamble {
-        content += &format!("// Preamble\n{preamble}\n\n");
+        content.push_str("// Preamble\n");
+        content.push(Fragment::from(preamble.as_str()).mapped("preamble"));
+        content.push_str("\n\n");
     }
 
     if let Some(document_content) = &document.content {
-        content += &format!(
-            "// Content\n{}",
-            node_to_typst(&document_content.root).unwrap_or_default()
-        )
+        content.push_str("// Content\n");
+        content.push(node_to_typst(&document_content.root, "root", lenient)?);
     }
 
-    content
+    Ok(content)
+}
+
+/// Deserializes JSON, keeping track of the path at which deserialization failed.
+fn from_json<'a, T: serde::Deserialize<'a>>(json: &'a str) -> Result<T, ConversionError> {
+    let deserializer = &mut serde_json::Deserializer::from_str(json);
+    serde_path_to_error::deserialize(deserializer).map_err(ConversionError::parse)
 }
 
-/// Converts the serialized TyX document to Typst code.
+/// Parses a serialized TyX document and migrates it to the current version of
+/// TyX, see [`migrate`], returning the migrated JSON and what changed.
+pub fn try_migrate_tyx_document(
+    document: &str,
+) -> Result<(serde_json::Value, MigrationReport), ConversionError> {
+    let mut document = from_json(document)?;
+    let report = migrate(&mut document).map_err(ConversionError::migration)?;
+    Ok((document, report))
+}
+
+/// Deserializes a TyX document migrated by [`try_migrate_tyx_document`],
+/// failing with the path of the first value that doesn't match the TyX schema.
+pub fn try_deserialize_tyx_document(
+    document: serde_json::Value,
+) -> Result<TyXDocument, ConversionError> {
+    serde_path_to_error::deserialize(document).map_err(ConversionError::parse)
+}
+
+/// Deserializes a TyX document, migrating it from older versions of TyX, and
+/// failing with the path of the first value that doesn't match the TyX schema.
+pub fn try_parse_tyx_document(document: &str) -> Result<TyXDocument, ConversionError> {
+    try_deserialize_tyx_document(try_migrate_tyx_document(document)?.0)
+}
+
+/// Converts the serialized TyX document to Typst code, see [`tyx_to_typst`].
+///
+/// # Panics
+///
+/// Panics if the document is malformed, see [`try_serialized_tyx_to_typst`].
 pub fn serialized_tyx_to_typst(document: &str) -> String {
-    let document = serde_json::from_str::<TyXDocument>(document).unwrap();
+    let document = try_parse_tyx_document(document).unwrap_or_else(|err| panic!("{err}"));
     tyx_to_typst(&document)
 }
 
-/// Converts the serialized TyX function data to Typst code.
+/// Converts the serialized TyX document to Typst code, failing if the document
+/// is malformed or can't be converted.
+pub fn try_serialized_tyx_to_typst(document: &str) -> Result<String, ConversionError> {
+    try_tyx_to_typst(&try_parse_tyx_document(document)?)
+}
+
+/// Converts the serialized TyX document to Typst code along with a
+/// [`SourceMap`], failing if the document is malformed or can't be converted.
+pub fn try_serialized_tyx_to_typst_with_source_map(
+    document: &str,
+) -> Result<(String, SourceMap), ConversionError> {
+    try_tyx_to_typst_with_source_map(&try_parse_tyx_document(document)?)
+}
+
+/// Converts the serialized TyX function data to Typst code, see
+/// [`stringify_function`].
+///
+/// # Panics
+///
+/// Panics if the parameters are malformed, see [`try_serialized_stringify_function`].
 pub fn serialized_stringify_function(
     name: Option<String>,
     position_parameters: &str,
     named_parameters: &str,
     include_content: bool,
 ) -> String {
-    let position_parameters = serde_json::from_str::<Vec<TyXValue>>(position_parameters).unwrap();
-    let named_parameters =
-        serde_json::from_str::<HashMap<String, TyXValue>>(named_parameters).unwrap();
+    let position_parameters =
+        from_json::<Vec<TyXValue>>(position_parameters).unwrap_or_else(|err| panic!("{err}"));
+    let named_parameters = from_json::<HashMap<String, TyXValue>>(named_parameters)
+        .unwrap_or_else(|err| panic!("{err}"));
     stringify_function(
         &name,
         &position_parameters,
@@ -450,6 +750,24 @@ pub fn serialized_stringify_function(
     )
 }
 
+/// Converts the serialized TyX function data to Typst code, failing if the
+/// parameters are malformed or can't be converted.
+pub fn try_serialized_stringify_function(
+    name: Option<String>,
+    position_parameters: &str,
+    named_parameters: &str,
+    include_content: bool,
+) -> Result<String, ConversionError> {
+    let position_parameters = from_json::<Vec<TyXValue>>(position_parameters)?;
+    let named_parameters = from_json::<HashMap<String, TyXValue>>(named_parameters)?;
+    try_stringify_function(
+        &name,
+        &position_parameters,
+        &named_parameters,
+        include_content,
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
@@ -558,4 +876,167 @@ mod tests {
         );
         assert_eq!(result, "fn()");
     }
+
+    // --- errors ---
+
+    #[test]
+    fn test_malformed_document_is_parse_error() {
+        let result = try_serialized_tyx_to_typst(r#"{"version": 1}"#);
+        assert!(matches!(
+            result,
+            Err(ConversionError::Parse { ref path, .. }) if path == "version"
+        ));
+    }
+
+    #[test]
+    fn test_unknown_document_field_is_parse_error() {
+        let result = try_parse_tyx_document(r#"{"version": "", "colour": "red"}"#);
+        assert!(matches!(result, Err(ConversionError::Parse { .. })));
+        assert!(try_parse_tyx_document(r#"{"version": ""}"#).is_ok());
+    }
+
+    #[test]
+    fn test_old_document_is_migrated() {
+        let (document, report) = try_migrate_tyx_document(
+            r#"{"version": "0.2.11", "preamble": null, "settings": {"language": null}}"#,
+        )
+        .unwrap();
+        let document = try_deserialize_tyx_document(document).unwrap();
+        assert_eq!(document.version, tyx_version::VERSION);
+        assert_eq!(document.preamble, None);
+        assert!(!report.is_empty());
+    }
+
+    #[test]
+    fn test_newer_document_is_parse_error() {
+        let result = try_parse_tyx_document(r#"{"version": "99.0.0"}"#);
+        assert!(matches!(
+            result,
+            Err(ConversionError::Parse { ref path, ref message })
+                if path == "version" && message.contains("newer")
+        ));
+    }
+
+    #[test]
+    fn test_check_list_is_unsupported() {
+        let document = r#"{"version": "", "content": {"root": {"type": "root", "children": [
+            {"type": "paragraph", "format": "", "children": []},
+            {"type": "list", "listType": "check", "start": 1, "children": [
+                {"type": "listitem", "value": 1, "children": []}
+            ]}
+        ]}}}"#;
+        let result = try_serialized_tyx_to_typst(document);
+        assert!(matches!(
+            result,
+            Err(ConversionError::UnsupportedNode { ref path, .. }) if path == "root/children/1"
+        ));
+    }
+
+    #[test]
+    fn test_empty_list_is_invalid() {
+        let document = r#"{"version": "", "content": {"root": {"type": "root", "children": [
+            {"type": "list", "listType": "bullet", "start": 1, "children": []}
+        ]}}}"#;
+        let result = try_serialized_tyx_to_typst(document);
+        assert!(matches!(
+            result,
+            Err(ConversionError::InvalidValue { ref path, .. }) if path == "root/children/0"
+        ));
+    }
+
+    #[test]
+    fn test_invalid_content_parameter_path() {
+        let result = try_serialized_stringify_function(
+            Some("footnote".into()),
+            r#"[{"type": "content", "value": {"type": "table", "children": []}}]"#,
+            "{}",
+            true,
+        );
+        assert_eq!(result.unwrap_err().path(), "positionParameters/0/value");
+    }
+
+    #[test]
+    fn test_infallible_conversions_leave_out_errors() {
+        let document = r#"{"version": "", "content": {"root": {"type": "root", "children": [
+            {"type": "paragraph", "format": "", "children": [{"type": "text", "format": 0, "text": "a"}]},
+            {"type": "list", "listType": "bullet", "start": 1, "children": []}
+        ]}}}"#;
+        assert!(serialized_tyx_to_typst(document).ends_with("// Content\na\n\n"));
+
+        let result = serialized_stringify_function(
+            Some("footnote".into()),
+            r#"[{"type": "content", "value": {"type": "table", "children": []}}]"#,
+            "{}",
+            true,
+        );
+        assert_eq!(result, "footnote()");
+    }
+
+    // --- text formats ---
+
+    #[test]
+    fn test_text_formats() {
+        let format = TextFormat::BOLD | TextFormat::HIGHLIGHT | TextFormat::UPPERCASE;
+        assert_eq!(
+            apply_text_format("a".into(), "a", format),
+            "#upper[#highlight[#strong[a]]]"
+        );
+        assert_eq!(
+            apply_text_format("a".into(), "a", TextFormat::CAPITALIZE),
+            "#smallcaps[a]"
+        );
+        assert_eq!(
+            apply_text_format("\\#".into(), "#", TextFormat::CODE | TextFormat::LOWERCASE),
+            "#raw(\"#\")"
+        );
+    }
+
+    #[test]
+    fn test_text_styles() {
+        assert_eq!(
+            apply_text_style("a".into(), "font-size: 12pt"),
+            "#text(size: 12pt)[a]"
+        );
+        assert_eq!(
+            apply_text_style(
+                "a".into(),
+                "color: red; font-family: 'Noto Serif', Libertinus, serif; font-size: 15px"
+            ),
+            "#text(fill: rgb(\"#ff0000\"), font: (\"Noto Serif\", \"Libertinus\"), size: 11.25pt)[a]"
+        );
+        assert_eq!(
+            apply_text_style("a".into(), "background-color: #ff08; font-size: 120%"),
+            "#text(size: 1.2em)[#highlight(fill: rgb(\"#ffff0088\"))[a]]"
+        );
+        assert_eq!(
+            apply_text_style("a".into(), "font-family: monospace; line-height: 2"),
+            "a"
+        );
+    }
+
+    // --- source map ---
+
+    #[test]
+    fn test_source_map_points_to_innermost_node() {
+        let document = r##"{"version": "", "preamble": "#let x = 1", "content": {"root": {"type": "root", "children": [
+            {"type": "paragraph", "format": "", "children": [{"type": "text", "format": 0, "text": "first"}]},
+            {"type": "paragraph", "format": "center", "children": [
+                {"type": "text", "format": 0, "text": "second"},
+                {"type": "math", "typst": "x^2", "inline": true}
+            ]}
+        ]}}}"##;
+        let (typst, source_map) = try_serialized_tyx_to_typst_with_source_map(document).unwrap();
+
+        let text = typst.find("second").unwrap();
+        assert_eq!(source_map.path_at(text), Some("root/children/1/children/0"));
+        let math = typst.find("x^2").unwrap();
+        assert_eq!(source_map.path_at(math), Some("root/children/1/children/1"));
+        let align = typst.find("#align").unwrap();
+        assert_eq!(source_map.path_at(align), Some("root/children/1"));
+        let preamble = typst.find("#let").unwrap();
+        assert_eq!(source_map.path_at(preamble), Some("preamble"));
+
+        let range = source_map.range_of("root/children/0/children/0").unwrap();
+        assert_eq!(&typst[range], "first");
+    }
 }
@@ -0,0 +1,110 @@
+//! Source maps from generated Typst code back to TyX node paths.
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+/// A byte range of generated Typst code and the path of the TyX element it was
+/// generated from, e.g. `root/children/3/children/1`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceMapping {
+    /// The byte range in the generated Typst code.
+    pub range: Range<usize>,
+    /// The path of the TyX element.
+    pub path: String,
+}
+
+/// Maps byte ranges of generated Typst code back to the TyX elements they were
+/// generated from.
+///
+/// Besides node paths, the document's settings and preamble are mapped to the
+/// `settings` and `preamble` paths respectively.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceMap {
+    /// The mappings, where an element's mapping always comes after the mappings
+    /// of the elements nested in it.
+    pub mappings: Vec<SourceMapping>,
+}
+
+impl SourceMap {
+    /// Returns the path of the innermost element whose code contains the given
+    /// byte offset.
+    pub fn path_at(&self, offset: usize) -> Option<&str> {
+        self.path_of(offset..offset + 1)
+    }
+
+    /// Returns the path of the innermost element whose code contains the whole
+    /// given byte range.
+    pub fn path_of(&self, range: Range<usize>) -> Option<&str> {
+        self.mappings
+            .iter()
+            .filter(|mapping| mapping.range.start <= range.start && range.end <= mapping.range.end)
+            .min_by_key(|mapping| mapping.range.len())
+            .map(|mapping| mapping.path.as_str())
+    }
+
+    /// Returns the byte range of the code generated from the element at the
+    /// given path.
+    pub fn range_of(&self, path: &str) -> Option<Range<usize>> {
+        self.mappings
+            .iter()
+            .find(|mapping| mapping.path == path)
+            .map(|mapping| mapping.range.clone())
+    }
+}
+
+/// Typst code being generated, along with the mappings of its parts.
+#[derive(Debug, Default)]
+pub(crate) struct Fragment {
+    pub(crate) code: String,
+    pub(crate) mappings: Vec<SourceMapping>,
+}
+
+impl Fragment {
+    /// Appends unmapped code.
+    pub(crate) fn push_str(&mut self, code: &str) {
+        self.code += code;
+    }
+
+    /// Appends another fragment, shifting its mappings accordingly.
+    pub(crate) fn push(&mut self, other: Fragment) {
+        let offset = self.code.len();
+        self.mappings
+            .extend(other.mappings.into_iter().map(|mapping| SourceMapping {
+                range: mapping.range.start + offset..mapping.range.end + offset,
+                path: mapping.path,
+            }));
+        self.code += &other.code;
+    }
+
+    /// Surrounds the fragment with the given unmapped code.
+    pub(crate) fn wrap(self, prefix: &str, suffix: &str) -> Fragment {
+        let mut result = Fragment::from(prefix);
+        result.push(self);
+        result.push_str(suffix);
+        result
+    }
+
+    /// Maps the whole fragment to the given path.
+    pub(crate) fn mapped(mut self, path: &str) -> Fragment {
+        self.mappings.push(SourceMapping {
+            range: 0..self.code.len(),
+            path: path.into(),
+        });
+        self
+    }
+}
+
+impl From<String> for Fragment {
+    fn from(code: String) -> Self {
+        Self {
+            code,
+            mappings: Vec::new(),
+        }
+    }
+}
+
+impl From<&str> for Fragment {
+    fn from(code: &str) -> Self {
+        Self::from(String::from(code))
+    }
+}
@@ -8,6 +8,13 @@ from typing import Dict, List, Literal, Optional, Union
 from pydantic import BaseModel, ConfigDict, Field, RootModel, conint
 
 
+class TyXTextFormat(RootModel[conint(ge=-9007199254740991, le=9007199254740991)]):
+    root: conint(ge=-9007199254740991, le=9007199254740991) = Field(
+        ...,
+        description="The formats of text as a bitmask of Lexical's text formats, e.g. 1 for bold, 2 for italic and 3 for both.",
+    )
+
+
 class TyXTextNode(BaseModel):
     """
     A node describing text.
@@ -15,7 +22,7 @@ class TyXTextNode(BaseModel):
 
     type: Literal["text"]
     text: str
-    format: conint(ge=-9007199254740991, le=9007199254740991)
+    format: TyXTextFormat
     style: Optional[str] = None
 
 
@@ -222,6 +229,29 @@ class TyXSettings(BaseModel):
     )
 
 
+class TyXPdfOptions(BaseModel):
+    """
+    Options for exporting the document to PDF.
+    """
+
+    model_config = ConfigDict(
+        extra="forbid",
+    )
+    standards: Optional[List[str]] = Field(
+        None,
+        description="The PDF standards the document must conform to, e.g. 'a-2b' or 'ua-1'.",
+    )
+    pages: Optional[str] = Field(
+        None, description="The pages to export, e.g. '1-3,5,8-'."
+    )
+    creationTimestamp: Optional[float] = Field(
+        None, description="The creation time of the document, in seconds since 1970."
+    )
+    identifier: Optional[str] = Field(
+        None, description="A stable identifier of the document across versions."
+    )
+
+
 class TyXDocumentSettings(BaseModel):
     """
     An object wrapping some common Typst document configuration options.
@@ -236,6 +266,8 @@ class TyXDocumentSettings(BaseModel):
     fontPaths: Optional[List[str]] = Field(
         None, description="Additional font paths for the Typst compiler."
     )
+    title: Optional[str] = Field(None, description="The title of the document.")
+    author: Optional[str] = Field(None, description="The author of the document.")
     language: Optional[str] = Field(None, description="The language of the document.")
     paper: Optional[str] = Field(None, description="The paper size of the document.")
     flipped: Optional[bool] = Field(
@@ -253,6 +285,9 @@ class TyXDocumentSettings(BaseModel):
     functions: Optional[Dict[str, FunctionDefinition]] = Field(
         None, description="Additional TyX function definitions."
     )
+    pdf: Optional[TyXPdfOptions] = Field(
+        None, description="The document's PDF export options."
+    )
 
 
 class TyXDocument(BaseModel):
@@ -273,6 +308,9 @@ class TyXDocument(BaseModel):
     filename: Optional[str] = Field(
         None, description="The filename of the document, unused."
     )
+    id: Optional[str] = Field(
+        None, description="A unique identifier of the open document, unused."
+    )
     content: Optional[TyXDocumentContent] = None
     dirty: Optional[bool] = Field(
         None,
@@ -323,6 +361,7 @@ class TyXListItemNode(BaseModel):
     type: Literal["listitem"]
     value: conint(ge=-9007199254740991, le=9007199254740991)
     children: List[TyXNode]
+    checked: Optional[bool] = None
 
 
 class TyXListNode(BaseModel):
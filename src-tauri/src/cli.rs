@@ -1,12 +1,54 @@
-use std::path::{Path, PathBuf};
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::Write,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+};
 
-use crate::pdf::typst_to_pdf;
-use clap::{Parser, ValueEnum};
+use crate::compile::{self, FontCache, parse_page_ranges};
+use crate::diagnostics::{Diagnostic, DiagnosticSeverity};
+use crate::fonts;
+use crate::pdf::{PdfExportOptions, parse_pdf_standard, typst_to_pdf};
+use crate::render::{Pages, typst_to_png, typst_to_svg};
+use crate::watch;
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use typst::layout::PageRanges;
+use typst_pdf::PdfStandard;
+use tyx_schema::{Issue, TyXDocument};
 
 #[derive(Debug, Clone, ValueEnum)]
 pub(crate) enum ExportFormat {
     Typst,
     Pdf,
+    Markdown,
+    Html,
+    Tyx,
+    Png,
+    Svg,
+}
+
+/// The schema of TyX documents, which the editor stores in them.
+const DOCUMENT_SCHEMA: &str = "https://tyx-editor.com/schemas/tyx-document.schema.json";
+
+/// The filename standing for the standard input or output.
+const STDIO: &str = "-";
+
+/// The placeholder for the page number in the output filenames of per-page
+/// formats.
+const PAGE_PLACEHOLDER: &str = "{n}";
+
+/// The output of an export.
+pub(crate) enum Output {
+    /// A single file.
+    File(Vec<u8>),
+    /// A file per page.
+    Pages(Pages),
 }
 
 impl ExportFormat {
@@ -14,65 +56,892 @@ impl ExportFormat {
         match self {
             Self::Pdf => ".pdf",
             Self::Typst => ".typ",
+            Self::Markdown => ".md",
+            Self::Html => ".html",
+            Self::Tyx => ".tyx",
+            Self::Png => ".png",
+            Self::Svg => ".svg",
         }
     }
 
-    pub(crate) fn export(&self, input: String, filename: &str) -> Vec<u8> {
-        let dirname = Path::new(filename).parent().unwrap().to_str().unwrap();
+    /// Returns the format of the given file extension, without the dot.
+    pub(crate) fn from_extension(extension: &str) -> Option<Self> {
+        Self::value_variants()
+            .iter()
+            .find(|format| format.extension().strip_prefix('.') == Some(extension))
+            .cloned()
+    }
+
+    /// Whether the format has a file per page.
+    pub(crate) fn is_per_page(&self) -> bool {
+        matches!(self, Self::Png | Self::Svg)
+    }
+
+    /// Exports the serialized TyX document, returning the output along with any
+    /// warnings, or the diagnostics explaining why the export failed.
+    pub(crate) fn export(
+        &self,
+        input: String,
+        filename: &str,
+        options: &ExportOptions,
+        fonts: &FontCache,
+    ) -> Result<(Output, Vec<Diagnostic>), Vec<Diagnostic>> {
+        let typst = || {
+            tyx_converters::try_serialized_tyx_to_typst_with_source_map(&input)
+                .map_err(|err| vec![Diagnostic::from(err)])
+        };
+        let root_path = options.root_path(filename);
+        let root_path = || root_path.clone();
+        // The standard input is compiled as a file in the root directory
+        let stdin_filename;
+        let filename = if filename == STDIO {
+            stdin_filename = root_path().join("stdin.tyx").to_string_lossy().into_owned();
+            &stdin_filename
+        } else {
+            filename
+        };
 
         match self {
-            Self::Typst => tyx_converters::serialized_tyx_to_typst(&input).into_bytes(),
-            Self::Pdf => {
-                let contents = tyx_converters::serialized_tyx_to_typst(&input);
-                typst_to_pdf(filename, &contents, PathBuf::from(dirname), vec![]).unwrap()
+            Self::Typst => {
+                typst().map(|(contents, _)| (Output::File(contents.into_bytes()), Vec::new()))
             }
+            Self::Pdf | Self::Png | Self::Svg => {
+                let (contents, source_map) = typst()?;
+                let attribute = |diagnostics: Vec<Diagnostic>| {
+                    diagnostics
+                        .into_iter()
+                        .map(|diagnostic| diagnostic.attribute(&source_map))
+                        .collect::<Vec<_>>()
+                };
+                let pages = options.pages.as_ref();
+                let font_paths = options.document_font_paths(&input);
+                let result = match self {
+                    Self::Png => typst_to_png(
+                        filename,
+                        &contents,
+                        root_path(),
+                        font_paths,
+                        fonts,
+                        options.ppi,
+                        pages,
+                    )
+                    .map(|(pages, warnings)| (Output::Pages(pages), warnings)),
+                    Self::Svg => {
+                        typst_to_svg(filename, &contents, root_path(), font_paths, fonts, pages)
+                            .map(|(pages, warnings)| (Output::Pages(pages), warnings))
+                    }
+                    _ => {
+                        let pdf_options = options.pdf_options(&input)?;
+                        typst_to_pdf(
+                            filename,
+                            &contents,
+                            root_path(),
+                            font_paths,
+                            fonts,
+                            &pdf_options,
+                        )
+                        .map(|(pdf, warnings)| (Output::File(pdf), warnings))
+                    }
+                };
+                result
+                    .map(|(output, warnings)| (output, attribute(warnings)))
+                    .map_err(attribute)
+            }
+            Self::Markdown => tyx_converters::try_serialized_tyx_to_markdown(&input)
+                .map(|markdown| (Output::File(markdown.into_bytes()), Vec::new()))
+                .map_err(|err| vec![err.into()]),
+            Self::Html => tyx_converters::try_serialized_tyx_to_html(&input)
+                .map(|html| (Output::File(html.into_bytes()), Vec::new()))
+                .map_err(|err| vec![err.into()]),
+            Self::Tyx => Ok((Output::File(input.into_bytes()), Vec::new())),
+        }
+    }
+}
+
+/// Options of the exports.
+#[derive(clap::Args, Debug, Clone)]
+pub(crate) struct ExportOptions {
+    /// The root directory of the Typst compiler, by default the directory of the file, or the current directory for the standard input
+    #[arg(long)]
+    pub(crate) root: Option<PathBuf>,
+    /// A directory of fonts to load, in addition to the fonts in the TyX configuration directory
+    #[arg(long = "font-path")]
+    pub(crate) font_paths: Vec<PathBuf>,
+    /// Export only these pages, e.g. `1-3,5,8-`
+    #[arg(long, value_parser = parse_page_ranges)]
+    pub(crate) pages: Option<PageRanges>,
+    /// The resolution of PNG exports, in pixels per inch
+    #[arg(long, default_value_t = 144.0)]
+    pub(crate) ppi: f32,
+    /// A PDF standard the PDF export must conform to, e.g. `a-2b` or `ua-1`, overriding the document's settings
+    #[arg(long = "pdf-standard", value_parser = parse_pdf_standard)]
+    pub(crate) pdf_standards: Vec<PdfStandard>,
+    /// The creation time of the PDF export, in seconds since 1970
+    #[arg(long)]
+    pub(crate) creation_timestamp: Option<i64>,
+    /// A stable identifier of the PDF export across versions of the document
+    #[arg(long)]
+    pub(crate) identifier: Option<String>,
+}
+
+impl ExportOptions {
+    /// Returns the root directory of the Typst compiler for the file.
+    fn root_path(&self, filename: &str) -> PathBuf {
+        self.root.clone().unwrap_or_else(|| {
+            Path::new(filename)
+                .parent()
+                .filter(|dirname| !dirname.as_os_str().is_empty())
+                .unwrap_or(Path::new("."))
+                .to_path_buf()
+        })
+    }
+
+    /// Returns the absolute font paths, since the compiler resolves relative
+    /// ones against the directory of the file.
+    fn font_paths(&self) -> Vec<String> {
+        self.font_paths
+            .iter()
+            .map(|path| {
+                std::path::absolute(path)
+                    .unwrap_or_else(|_| path.clone())
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect()
+    }
+
+    /// Returns the font paths in the settings of the serialized TyX document,
+    /// which are relative to the directory of the file like in the editor,
+    /// followed by the command line ones.
+    fn document_font_paths(&self, input: &str) -> Vec<String> {
+        let mut font_paths = tyx_converters::try_parse_tyx_document(input)
+            .ok()
+            .and_then(|document| document.settings)
+            .map(|settings| settings.font_paths)
+            .unwrap_or_default();
+        font_paths.extend(self.font_paths());
+        font_paths
+    }
+
+    /// Returns the PDF export options in the settings of the serialized TyX
+    /// document, overridden by the command line ones.
+    fn pdf_options(&self, input: &str) -> Result<PdfExportOptions, Vec<Diagnostic>> {
+        let settings = tyx_converters::try_parse_tyx_document(input)
+            .ok()
+            .and_then(|document| document.settings)
+            .and_then(|settings| settings.pdf)
+            .unwrap_or_default();
+        let mut options =
+            PdfExportOptions::try_from(&settings).map_err(|err| vec![Diagnostic::error(err)])?;
+        if !self.pdf_standards.is_empty() {
+            options.standards = self.pdf_standards.clone();
+        }
+        if self.pages.is_some() {
+            options.pages = self.pages.clone();
+        }
+        if self.creation_timestamp.is_some() {
+            options.creation_timestamp = self.creation_timestamp;
         }
+        if self.identifier.is_some() {
+            options.identifier = self.identifier.clone();
+        }
+        Ok(options)
     }
 }
 
 /// Simple program to greet a person
 #[derive(Parser, Debug)]
-#[command(version = tyx_version::VERSION, about, long_about = None, display_name="TyX")]
+#[command(version = tyx_version::VERSION, about, long_about = None, display_name="TyX", args_conflicts_with_subcommands = true)]
 pub(crate) struct Args {
-    /// Files to open or export.
+    #[command(subcommand)]
+    pub(crate) command: Option<Command>,
+    /// Files to open or export, `-` exports the standard input.
     pub(crate) files: Vec<String>,
     /// Export as this file format, the default output filenames are just replacing the file extension
     #[arg(short, long)]
     pub(crate) export: Option<ExportFormat>,
-    /// Save the output to this filename, requires files to be only one.
+    /// Save the output to this filename, requires files to be only one. For PNG and SVG, `{n}` is replaced by the page number, and `-` writes to the standard output
     #[arg(short, long)]
     pub(crate) output: Option<String>,
+    /// Save the outputs to this directory, creating it if needed, instead of next to the files. The standard input is still exported to the standard output
+    #[arg(long, requires = "export", conflicts_with = "output")]
+    pub(crate) output_dir: Option<PathBuf>,
+    /// How many files to export in parallel, by default the number of CPUs
+    #[arg(short, long)]
+    pub(crate) jobs: Option<NonZeroUsize>,
+    /// Write a JSON report of the export of each file to this filename, `-` writes it to the standard output
+    #[arg(long, requires = "export")]
+    pub(crate) summary: Option<String>,
+    /// Export again whenever the files, their root directory, fonts or images change, requires --export
+    #[arg(short, long, requires = "export")]
+    pub(crate) watch: bool,
+    #[command(flatten)]
+    pub(crate) export_options: ExportOptions,
+}
+
+/// The subcommands, which run without opening the editor.
+#[derive(Subcommand, Debug)]
+pub(crate) enum Command {
+    /// Convert a file, picking the formats by the extensions of the filenames
+    Convert {
+        /// The file to convert, a TyX, Typst or Markdown file, or `-` for a TyX document from the standard input
+        input: String,
+        /// The converted file, e.g. a TyX, Typst, Markdown, HTML or PDF file
+        output: String,
+        #[command(flatten)]
+        options: ExportOptions,
+    },
+    /// Check that TyX documents can be read, migrating them to the current version, and have no semantic errors
+    Validate {
+        /// The TyX documents to check
+        #[arg(required = true)]
+        files: Vec<String>,
+    },
+    /// Normalize the JSON layout of TyX documents, migrating them to the current version
+    Fmt {
+        /// The TyX documents to format
+        #[arg(required = true)]
+        files: Vec<String>,
+        /// Only check that the documents are formatted, without changing them
+        #[arg(long)]
+        check: bool,
+    },
+    /// Print the version of TyX, and the node statistics and settings of TyX documents
+    Info {
+        /// The TyX documents to describe
+        files: Vec<String>,
+    },
+    /// List the font families available to documents, from the TyX fonts directory and the system
+    Fonts {
+        /// A directory of fonts to load in addition
+        #[arg(long = "font-path")]
+        font_paths: Vec<PathBuf>,
+        /// Also list the variants of each family, with the number of code points they cover
+        #[arg(long)]
+        variants: bool,
+        /// Print the families, their variants and coverage as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+/// The exit code of the commands that failed.
+const FAILURE: i32 = 1;
+
+impl Command {
+    /// Runs the subcommand with the given font cache, returning its exit code.
+    fn run(&self, fonts: &FontCache) -> i32 {
+        let succeeded = match self {
+            Self::Convert {
+                input,
+                output,
+                options,
+            } => convert(input, output, options, fonts),
+            Self::Validate { files } => all_files(files, validate_file),
+            Self::Fmt { files, check } => all_files(files, |file| format_file(file, *check)),
+            Self::Info { files } => {
+                println!("TyX {}", tyx_version::VERSION);
+                all_files(files, describe_file)
+            }
+            Self::Fonts {
+                font_paths,
+                variants,
+                json,
+            } => print_fonts(font_paths, *variants, *json, fonts),
+        };
+
+        if succeeded { 0 } else { FAILURE }
+    }
 }
 
 impl Args {
-    pub(crate) fn process(&self) -> bool {
-        let mut should_exit = false;
+    /// Runs the subcommand or the export, returning the exit code, or `None`
+    /// if the editor should open instead.
+    pub(crate) fn process(&self) -> Option<i32> {
+        let fonts = FontCache::default();
+        if let Some(command) = &self.command {
+            return Some(command.run(&fonts));
+        }
+        let format = self.export.as_ref()?;
+        if self.files.is_empty() {
+            return None;
+        }
+        if self.output.is_some() && self.files.len() != 1 {
+            eprintln!("error: --output requires exporting a single file");
+            return Some(FAILURE);
+        }
+        if self.watch && self.files.iter().any(|file| file == STDIO) {
+            eprintln!("error: the standard input can't be watched");
+            return Some(FAILURE);
+        }
 
-        let output_filename = match &self.output {
-            Some(output) => {
-                assert!(self.files.len() == 1);
-                Some(output)
+        if let Some(output_dir) = &self.output_dir
+            && let Err(err) = std::fs::create_dir_all(output_dir)
+        {
+            eprintln!("error: failed to create {}: {err}", output_dir.display());
+            return Some(FAILURE);
+        }
+
+        let export = || {
+            let reports = self.export_files(format, &fonts);
+            if self.files.len() > 1 {
+                let failed = reports.iter().filter(|report| !report.succeeded).count();
+                eprintln!(
+                    "Exported {} of {} files",
+                    reports.len() - failed,
+                    reports.len()
+                );
+            }
+            if let Some(summary) = &self.summary {
+                write_summary(&reports, summary);
             }
-            None => None,
+            let failed = reports.iter().any(|report| !report.succeeded);
+            let outputs = reports
+                .into_iter()
+                .flat_map(|report| report.outputs)
+                .filter_map(|output| dunce::canonicalize(output).ok())
+                .collect::<Vec<_>>();
+            (outputs, failed)
         };
-        for file in self.files.iter() {
-            let contents = std::fs::read_to_string(file).unwrap();
-            if let Some(ref format) = self.export {
-                let file_base = match file.strip_suffix(".tyx") {
-                    Some(file) => file,
-                    None => {
-                        println!("warning: file {file} might not be a TyX document!");
-                        file
+        let (mut outputs, mut failed) = export();
+        if !self.watch {
+            return Some(if failed { FAILURE } else { 0 });
+        }
+
+        // The fonts are only loaded again when they change, and Typst memoizes
+        // compilation within the process, so each rebuild only recompiles what
+        // changed
+        println!("Watching for changes, press Ctrl+C to stop");
+        let result = watch::watch(self.watched_paths(), |changed| {
+            // Writing the outputs shouldn't trigger another export
+            if !changed.iter().all(|path| outputs.contains(path)) {
+                println!();
+                fonts.reset(changed);
+                (outputs, failed) = export();
+            }
+            // The edits may reference other images
+            self.watched_paths()
+        });
+        match result {
+            Ok(()) => Some(if failed { FAILURE } else { 0 }),
+            Err(err) => {
+                eprintln!("error: failed to watch for changes: {err}");
+                Some(FAILURE)
+            }
+        }
+    }
+
+    /// Exports all the files in parallel, sharing the loaded fonts, and prints
+    /// the report of each file as soon as it is exported. The reports are
+    /// returned in the order of the files.
+    fn export_files(&self, format: &ExportFormat, fonts: &FontCache) -> Vec<FileReport> {
+        let jobs = self
+            .jobs
+            .or_else(|| thread::available_parallelism().ok())
+            .map_or(1, NonZeroUsize::get)
+            .min(self.files.len());
+        let files = self
+            .files
+            .iter()
+            .zip(self.output_filenames(format))
+            .collect::<Vec<_>>();
+        let next = AtomicUsize::new(0);
+        let reports = Mutex::new(Vec::with_capacity(self.files.len()));
+        thread::scope(|scope| {
+            for _ in 0..jobs {
+                scope.spawn(|| {
+                    while let Some((file, output)) = files.get(next.fetch_add(1, Ordering::Relaxed))
+                    {
+                        let report = match output {
+                            Ok(output) => {
+                                export_file(format, file, output, &self.export_options, fonts)
+                            }
+                            Err(err) => FileReport {
+                                file: file.to_string(),
+                                succeeded: false,
+                                outputs: Vec::new(),
+                                diagnostics: vec![Diagnostic::error(err)],
+                            },
+                        };
+                        // Printing while locked keeps the reports from interleaving
+                        let mut reports = reports.lock().unwrap();
+                        report.print();
+                        reports.push(report);
                     }
-                };
-                should_exit = true;
-                let default_filename = String::from(file_base) + format.extension();
-                let final_output_filename = output_filename.unwrap_or(&default_filename);
-                println!("Exported to {final_output_filename}");
-                std::fs::write(final_output_filename, format.export(contents, file)).unwrap();
+                });
+            }
+        });
+
+        let mut reports = reports.into_inner().unwrap();
+        reports.sort_by_key(|report| self.files.iter().position(|file| *file == report.file));
+        reports
+    }
+
+    /// Returns the output filename of each file, or an error if other files
+    /// would be exported to the same filename, since they would overwrite each
+    /// other.
+    fn output_filenames(&self, format: &ExportFormat) -> Vec<Result<String, String>> {
+        let filenames = self
+            .files
+            .iter()
+            .map(|file| match (&self.output, &self.output_dir) {
+                (Some(output), _) => output.clone(),
+                // The standard input is still exported to the standard output
+                (None, Some(output_dir)) if file != STDIO => output_dir
+                    .join(default_output(format, file))
+                    .to_string_lossy()
+                    .into_owned(),
+                _ => default_filename(format, file),
+            })
+            .collect::<Vec<_>>();
+
+        let mut indices = HashMap::<&str, Vec<usize>>::new();
+        for (index, filename) in filenames.iter().enumerate() {
+            if filename != STDIO {
+                indices.entry(filename).or_default().push(index);
+            }
+        }
+        filenames
+            .iter()
+            .enumerate()
+            .map(|(index, filename)| {
+                let others = indices
+                    .get(filename.as_str())
+                    .into_iter()
+                    .flatten()
+                    .filter(|other| **other != index)
+                    .map(|other| self.files[*other].as_str())
+                    .collect::<Vec<_>>();
+                if others.is_empty() {
+                    Ok(filename.clone())
+                } else {
+                    Err(format!(
+                        "{} would also be exported to {filename}",
+                        others.join(", ")
+                    ))
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the paths to watch for changes to the exported files.
+    fn watched_paths(&self) -> Vec<PathBuf> {
+        let mut paths = Vec::new();
+        for file in &self.files {
+            let Ok(path) = dunce::canonicalize(file) else {
+                continue;
+            };
+            let dirname = path.parent().unwrap_or(Path::new(""));
+            let root = dunce::canonicalize(self.export_options.root_path(file))
+                .unwrap_or_else(|_| dirname.to_path_buf());
+            let contents = std::fs::read_to_string(&path).unwrap_or_default();
+            let font_paths = self
+                .export_options
+                .document_font_paths(&contents)
+                .iter()
+                .filter_map(|font_path| dunce::canonicalize(dirname.join(font_path)).ok())
+                .collect::<Vec<_>>();
+            for path in watch::watched_paths(&path, &root, &font_paths, &contents) {
+                if !paths
+                    .iter()
+                    .any(|watched: &PathBuf| path.starts_with(watched))
+                {
+                    paths.push(path);
+                }
+            }
+        }
+        paths
+    }
+}
+
+/// Returns the filename without its extension.
+fn file_base(file: &str) -> &str {
+    match Path::new(file).extension() {
+        Some(extension) => &file[..file.len() - extension.len() - 1],
+        None => file,
+    }
+}
+
+/// Reads a file as a serialized TyX document, importing it according to its
+/// extension, or the standard input as a TyX document.
+fn read_document(file: &str, options: &ExportOptions, fonts: &FontCache) -> Result<String, String> {
+    if file == STDIO {
+        return std::io::read_to_string(std::io::stdin())
+            .map_err(|err| format!("failed to read the standard input: {err}"));
+    }
+    let contents =
+        std::fs::read_to_string(file).map_err(|err| format!("failed to read {file}: {err}"))?;
+    let document = match Path::new(file).extension().and_then(|ext| ext.to_str()) {
+        Some("tyx") => return Ok(contents),
+        Some("md" | "markdown") => tyx_converters::markdown_to_tyx(&contents),
+        Some("typ") => {
+            // Imports the file from its world, like opening it in the editor
+            let world = compile::world(file, options.root_path(file), options.font_paths(), fonts)
+                .map_err(|diagnostics| {
+                    diagnostics
+                        .iter()
+                        .map(|diagnostic| diagnostic.message.as_str())
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                })?;
+            tyx_converters::typst_syntax_to_tyx(Arc::new(world))
+                .ok_or_else(|| format!("failed to import {file}"))?
+        }
+        Some("html") => return Err(format!("{file} can't be imported from HTML")),
+        _ => {
+            eprintln!("warning: file {file} might not be a TyX document!");
+            return Ok(contents);
+        }
+    };
+    serde_json::to_string(&document).map_err(|err| format!("failed to import {file}: {err}"))
+}
+
+/// Reads a TyX document and migrates it to the current version of TyX,
+/// mentioning what changed, returning its contents, the migrated JSON and the
+/// deserialized document.
+fn read_tyx_document(file: &str) -> Result<(String, serde_json::Value, TyXDocument), String> {
+    let contents =
+        std::fs::read_to_string(file).map_err(|err| format!("failed to read {file}: {err}"))?;
+    let (json, report) = tyx_converters::try_migrate_tyx_document(&contents)
+        .map_err(|err| format!("{file}: {err}"))?;
+    if !report.is_empty() {
+        eprintln!("note: {file}: {report}");
+    }
+    let document = tyx_converters::try_deserialize_tyx_document(json.clone())
+        .map_err(|err| format!("{file}: {err}"))?;
+    Ok((contents, json, document))
+}
+
+/// The report of exporting a file.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FileReport {
+    /// The exported file.
+    file: String,
+    /// Whether the export succeeded.
+    succeeded: bool,
+    /// The written files, if the export succeeded.
+    outputs: Vec<PathBuf>,
+    /// The errors and warnings of the export.
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl FileReport {
+    /// Prints the diagnostics and the written files.
+    fn print(&self) {
+        for diagnostic in &self.diagnostics {
+            eprintln!("{diagnostic}");
+        }
+        if self.succeeded {
+            for output in &self.outputs {
+                eprintln!("Exported to {}", output.display());
+            }
+        } else {
+            eprintln!("error: failed to export {}", self.file);
+        }
+    }
+}
+
+/// Returns the default output filename of a file, replacing its extension.
+fn default_output(format: &ExportFormat, file: &str) -> String {
+    let base = Path::new(file_base(file))
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(file);
+    if file == STDIO {
+        STDIO.into()
+    } else if format.is_per_page() {
+        format!("{base}-{PAGE_PLACEHOLDER}{}", format.extension())
+    } else {
+        String::from(base) + format.extension()
+    }
+}
+
+/// Returns the default output filename of a file, next to it.
+fn default_filename(format: &ExportFormat, file: &str) -> String {
+    match Path::new(file).parent() {
+        Some(dirname) if file != STDIO => dirname
+            .join(default_output(format, file))
+            .to_string_lossy()
+            .into_owned(),
+        _ => default_output(format, file),
+    }
+}
+
+/// Exports a file to the given output filename, and reports the written files
+/// and the diagnostics.
+fn export_file(
+    format: &ExportFormat,
+    file: &str,
+    output_filename: &str,
+    options: &ExportOptions,
+    fonts: &FontCache,
+) -> FileReport {
+    let mut report = FileReport {
+        file: file.to_string(),
+        succeeded: false,
+        outputs: Vec::new(),
+        diagnostics: Vec::new(),
+    };
+    let contents = match read_document(file, options, fonts) {
+        Ok(contents) => contents,
+        Err(err) => {
+            report.diagnostics.push(Diagnostic::error(err));
+            return report;
+        }
+    };
+    if output_filename == file && file != STDIO {
+        report.diagnostics.push(Diagnostic::error(format!(
+            "exporting {file} would overwrite it, use --output"
+        )));
+        return report;
+    }
+    match format.export(contents, file, options, fonts) {
+        Ok((output, warnings)) => {
+            report.diagnostics = warnings;
+            match write_output(output, output_filename) {
+                Ok(outputs) => {
+                    report.succeeded = true;
+                    report.outputs = outputs;
+                }
+                Err(err) => report.diagnostics.push(Diagnostic::error(err)),
+            }
+        }
+        Err(diagnostics) => report.diagnostics = diagnostics,
+    }
+    report
+}
+
+/// Writes the JSON summary of the exports to a file or the standard output.
+fn write_summary(reports: &[FileReport], filename: &str) {
+    let count = |severity| {
+        reports
+            .iter()
+            .flat_map(|report| &report.diagnostics)
+            .filter(|diagnostic| diagnostic.severity == severity)
+            .count()
+    };
+    let summary = serde_json::json!({
+        "succeeded": reports.iter().filter(|report| report.succeeded).count(),
+        "failed": reports.iter().filter(|report| !report.succeeded).count(),
+        "errors": count(DiagnosticSeverity::Error),
+        "warnings": count(DiagnosticSeverity::Warning),
+        "files": reports,
+    });
+    let summary = serde_json::to_string_pretty(&summary).unwrap() + "\n";
+    let result = if filename == STDIO {
+        std::io::stdout().write_all(summary.as_bytes())
+    } else {
+        std::fs::write(filename, summary)
+    };
+    if let Err(err) = result {
+        eprintln!("error: failed to write the summary to {filename}: {err}");
+    }
+}
+
+/// Converts a file to the format of the output's extension, returning whether
+/// it succeeded.
+fn convert(input: &str, output: &str, options: &ExportOptions, fonts: &FontCache) -> bool {
+    let extension = Path::new(output)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("");
+    let Some(format) = ExportFormat::from_extension(extension) else {
+        eprintln!("error: can't convert to unknown extension {extension:?}");
+        return false;
+    };
+    let report = export_file(&format, input, output, options, fonts);
+    report.print();
+    report.succeeded
+}
+
+/// Runs the function on all the files, returning whether it succeeded for all
+/// of them.
+fn all_files(files: &[String], function: impl Fn(&str) -> bool) -> bool {
+    files
+        .iter()
+        .fold(true, |succeeded, file| function(file) && succeeded)
+}
+
+/// Validates a TyX document, printing its issues, and returning whether it is
+/// valid, i.e. whether it has no errors.
+fn validate_file(file: &str) -> bool {
+    let document = match read_tyx_document(file) {
+        Ok((_, _, document)) => document,
+        Err(err) => {
+            eprintln!("error: {err}");
+            return false;
+        }
+    };
+    let issues = tyx_schema::validate(&document);
+    let valid = !issues.iter().any(Issue::is_error);
+    for issue in issues {
+        eprintln!("{}", Diagnostic::from(issue));
+    }
+    if valid {
+        println!("{file} is valid");
+    } else {
+        eprintln!("error: {file} is invalid");
+    }
+    valid
+}
+
+/// Formats a TyX document, migrating it to the current version of TyX,
+/// returning whether it succeeded, or when checking, whether the document was
+/// already formatted.
+fn format_file(file: &str, check: bool) -> bool {
+    let (contents, document) = match read_tyx_document(file) {
+        Ok((contents, serde_json::Value::Object(document), _)) => (contents, document),
+        Ok(_) => {
+            eprintln!("error: {file} isn't a JSON object");
+            return false;
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            return false;
+        }
+    };
+    // Like the editor, store the schema first but not the editor's state, and
+    // keep the order of the other keys
+    let mut formatted = serde_json::Map::new();
+    formatted.insert("$schema".into(), DOCUMENT_SCHEMA.into());
+    formatted.extend(
+        document
+            .into_iter()
+            .filter(|(key, _)| !matches!(key.as_str(), "$schema" | "dirty" | "filename" | "id")),
+    );
+    let formatted = match serde_json::to_string_pretty(&formatted) {
+        Ok(formatted) => formatted + "\n",
+        Err(err) => {
+            eprintln!("error: failed to format {file}: {err}");
+            return false;
+        }
+    };
+
+    if formatted == contents {
+        return true;
+    }
+    if check {
+        eprintln!("{file} isn't formatted");
+        return false;
+    }
+    if let Err(err) = std::fs::write(file, formatted) {
+        eprintln!("error: failed to write {file}: {err}");
+        return false;
+    }
+    println!("Formatted {file}");
+    true
+}
+
+/// Prints the version, node statistics and settings of a TyX document,
+/// returning whether it succeeded.
+fn describe_file(file: &str) -> bool {
+    let document = match read_tyx_document(file) {
+        Ok((_, _, document)) => document,
+        Err(err) => {
+            eprintln!("error: {err}");
+            return false;
+        }
+    };
+
+    println!("\n{file}");
+    println!("  version: {}", document.version);
+    let mut counts = BTreeMap::<_, usize>::new();
+    for (_, node) in document.nodes() {
+        *counts.entry(node.kind()).or_default() += 1;
+    }
+    println!("  nodes: {}", counts.values().sum::<usize>());
+    for (kind, count) in counts {
+        println!("    {kind}: {count}");
+    }
+    let settings = document
+        .settings
+        .and_then(|settings| serde_json::to_string_pretty(&settings).ok())
+        .unwrap_or_else(|| "{}".into());
+    println!("  settings: {}", settings.replace('\n', "\n  "));
+    true
+}
+
+/// Prints the available font families, returning whether loading the fonts
+/// succeeded.
+fn print_fonts(font_paths: &[PathBuf], variants: bool, json: bool, fonts: &FontCache) -> bool {
+    let font_paths = font_paths
+        .iter()
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    let families = match fonts::list_fonts(Path::new("."), &font_paths, fonts) {
+        Ok(families) => families,
+        Err(diagnostics) => {
+            for diagnostic in diagnostics {
+                eprintln!("{diagnostic}");
+            }
+            return false;
+        }
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&families).unwrap());
+        return true;
+    }
+    for family in families {
+        println!("{}", family.name);
+        if variants {
+            for variant in &family.variants {
+                println!(
+                    "  - {}, weight {}, stretch {}%, {} code points",
+                    variant.style,
+                    variant.weight,
+                    variant.stretch * 100.0,
+                    variant.codepoints()
+                );
+            }
+        }
+    }
+    true
+}
+
+/// Writes the output of an export, replacing the page placeholder in the
+/// filename of each page, and returns the paths of the written files.
+fn write_output(output: Output, filename: &str) -> Result<Vec<PathBuf>, String> {
+    if filename == STDIO {
+        let contents = match output {
+            Output::File(contents) => contents,
+            Output::Pages(mut pages) if pages.len() == 1 => pages.remove(0).1,
+            Output::Pages(_) => {
+                return Err("several pages can't be written to the standard output".into());
+            }
+        };
+        std::io::stdout()
+            .write_all(&contents)
+            .map_err(|err| format!("failed to write to the standard output: {err}"))?;
+        return Ok(Vec::new());
+    }
+
+    let files = match output {
+        Output::File(contents) => vec![(filename.to_string(), contents)],
+        Output::Pages(pages) => {
+            if pages.len() > 1 && !filename.contains(PAGE_PLACEHOLDER) {
+                return Err(format!(
+                    "exporting several pages to {filename} requires a {PAGE_PLACEHOLDER} placeholder"
+                ));
             }
+            pages
+                .into_iter()
+                .map(|(number, contents)| {
+                    (
+                        filename.replace(PAGE_PLACEHOLDER, &number.to_string()),
+                        contents,
+                    )
+                })
+                .collect()
         }
+    };
 
-        should_exit
+    let mut written = Vec::new();
+    for (filename, contents) in files {
+        std::fs::write(&filename, contents)
+            .map_err(|err| format!("failed to write {filename}: {err}"))?;
+        written.push(PathBuf::from(filename));
     }
+    Ok(written)
 }
@@ -9,11 +9,16 @@ use base64::{Engine, engine::general_purpose::STANDARD};
 
 use tauri::{Emitter, Manager};
 
-use crate::pdf;
+use crate::compile::{self, FontCache};
+use crate::diagnostics::Diagnostic;
+use crate::fonts::{self, FontFamily};
+use crate::pdf::{self, PdfExportOptions};
+use crate::server::Server;
 use crate::utils;
 use tauri_plugin_dialog::DialogExt;
-use tinymist_project::{CompileOnceArgs, EntryReader, TaskInputs, WorldProvider};
+use tinymist_project::EntryReader;
 use typstyle_core::Typstyle;
+use tyx_schema::TyXPdfOptions;
 
 #[tauri::command]
 pub(crate) fn save(filename: &str, content: &str, format: bool) {
@@ -52,37 +57,66 @@ pub(crate) fn saveas(handle: tauri::AppHandle) {
 
 pub(crate) fn openfile(handle: &tauri::AppHandle, path: &Path, include_filename: bool) {
     let buffer = if path.extension().is_some_and(|ext| ext == "typ") {
-        let Some(dir) = path.parent() else {
-            eprintln!("failed to get the base directory of the file to open");
+        let dir = path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let Some(filename) = path.to_str() else {
+            eprintln!("failed to read the path of the file to open");
             return;
         };
-        let universe = CompileOnceArgs {
-            root: Some(PathBuf::from(dir)),
-            input: Some(path.to_str().unwrap().into()),
-            ..CompileOnceArgs::default()
-        }
-        .resolve()
-        .unwrap();
-        let Ok(entry) = universe.entry_state().try_select_path_in_workspace(path) else {
-            eprintln!("not selected path {:?}", universe.entry_state());
-            return;
+        let world = match compile::world(
+            filename,
+            PathBuf::from(dir),
+            Vec::new(),
+            &FontCache::default(),
+        ) {
+            Ok(world) => world,
+            Err(diagnostics) => {
+                for diagnostic in diagnostics {
+                    eprintln!("{diagnostic}");
+                }
+                return;
+            }
         };
-        let world = universe.snapshot_with(Some(TaskInputs {
-            entry,
-            ..TaskInputs::default()
-        }));
         if world.main_id().is_none() {
             eprintln!("no main id");
             return;
         }
 
-        let Some(doc) = tyx_converters::typst_to_tyx(Arc::new(world)) else {
+        let Some(doc) = tyx_converters::typst_syntax_to_tyx(Arc::new(world)) else {
             return;
         };
         serde_json::to_string(&doc).unwrap()
+    } else if path
+        .extension()
+        .is_some_and(|ext| ext == "md" || ext == "markdown")
+    {
+        let markdown = std::fs::read_to_string(path).unwrap();
+        serde_json::to_string(&tyx_converters::markdown_to_tyx(&markdown)).unwrap()
     } else {
         // tyx
-        std::fs::read_to_string(path).unwrap()
+        let contents = std::fs::read_to_string(path).unwrap();
+        match tyx_converters::try_migrate_tyx_document(&contents) {
+            Ok((document, report)) => {
+                if !report.is_empty() {
+                    eprintln!("{}: {report}", path.display());
+                }
+                document.to_string()
+            }
+            Err(err) => {
+                handle
+                    .emit(
+                        "diagnostics",
+                        (
+                            path.to_str().unwrap(),
+                            vec![Diagnostic::error(err.to_string())],
+                        ),
+                    )
+                    .unwrap();
+                return;
+            }
+        }
     };
 
     handle
@@ -104,6 +138,7 @@ pub(crate) fn open(handle: tauri::AppHandle, filename: &str) {
         .file()
         .add_filter("TyX", &["tyx"])
         .add_filter("Typst", &["typ"])
+        .add_filter("Markdown", &["md", "markdown"])
         .pick_file(move |f| {
             if let Some(f) = f
                 && let Some(path) = f.as_path()
@@ -184,57 +219,193 @@ pub(crate) fn readimage(filename: &str, image: &str) -> String {
     String::from("data:") + mimetype + ";base64," + STANDARD.encode(&bytes).as_str()
 }
 
-#[tauri::command]
-pub(crate) fn preview(
-    handle: tauri::AppHandle,
-    filename: &str,
-    content: &str,
-    root: &str,
-    font_paths: Vec<String>,
-    open: bool,
-) -> String {
-    let filename = if filename.is_empty() {
+/// Returns the filename to compile a document as, and its root directory.
+/// Untitled documents are named without a directory, e.g. `Untitled-2.tyx`, and
+/// are compiled as virtual files in the temporary directory, which only exist
+/// in the compiler's memory.
+fn compiled_paths(handle: &tauri::AppHandle, filename: &str, root: &str) -> (String, PathBuf) {
+    let untitled = Path::new(filename)
+        .parent()
+        .is_none_or(|dirname| dirname.as_os_str().is_empty());
+    let filename = if untitled {
         let temp_dir = handle.path().temp_dir().unwrap();
         if !temp_dir.is_dir() {
             create_dir_all(&temp_dir).unwrap();
         }
 
-        temp_dir.join("Untitled.tyx").to_str().unwrap().to_string()
+        let name = if filename.is_empty() {
+            "Untitled.tyx"
+        } else {
+            filename
+        };
+        temp_dir.join(name).to_str().unwrap().to_string()
     } else {
         filename.to_string()
     };
-    let basename = filename
-        .strip_suffix(".tyx")
-        .unwrap_or(&filename)
-        .to_string();
 
-    let dirname = Path::new(basename.as_str())
+    let mut root_path = Path::new(&filename)
         .parent()
-        .unwrap()
-        .to_str()
-        .unwrap();
-    let pdf_file = basename.clone() + ".pdf";
-
-    let mut root_path = PathBuf::from(dirname);
+        .unwrap_or(Path::new(""))
+        .to_path_buf();
     if !root.is_empty() {
         root_path.push(PathBuf::from(root));
     }
+    (filename, root_path)
+}
 
-    let pdf = match pdf::typst_to_pdf(&filename, content, root_path, font_paths) {
-        Ok(pdf) => pdf,
-        Err(e) => {
-            return e;
+/// Compiles the document in its session to a PDF, off the main thread,
+/// emitting the `compiled` event with the PDF file once it is written, and
+/// returning the errors and warnings of the compilation.
+#[tauri::command]
+pub(crate) async fn preview(
+    handle: tauri::AppHandle,
+    filename: String,
+    document: String,
+    root: String,
+    font_paths: Vec<String>,
+    pdf_options: TyXPdfOptions,
+    open: bool,
+) -> Vec<Diagnostic> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let (filename, root_path) = compiled_paths(&handle, &filename, &root);
+        let pdf_file = filename
+            .strip_suffix(".tyx")
+            .unwrap_or(&filename)
+            .to_string()
+            + ".pdf";
+
+        match compile_pdf(
+            &handle.state::<Server>(),
+            &filename,
+            &document,
+            root_path,
+            font_paths,
+            &pdf_options,
+        ) {
+            Ok((pdf, warnings)) => {
+                let mut f = File::create(&pdf_file).unwrap();
+                f.write_all(&pdf).unwrap();
+
+                if open {
+                    let _ = open::that(String::from("file://") + &pdf_file);
+                }
+                handle.emit("compiled", (&filename, &pdf_file)).unwrap();
+                warnings
+            }
+            Err(diagnostics) => diagnostics,
         }
+    })
+    .await
+    .unwrap_or_else(|err| vec![Diagnostic::error(err.to_string())])
+}
+
+/// Compiles the serialized TyX document to a PDF in its session, returning it
+/// along with any warnings, or all the errors and warnings, attributed to the
+/// TyX nodes they originate from.
+fn compile_pdf(
+    server: &Server,
+    filename: &str,
+    document: &str,
+    root_path: PathBuf,
+    font_paths: Vec<String>,
+    pdf_options: &TyXPdfOptions,
+) -> Result<(Vec<u8>, Vec<Diagnostic>), Vec<Diagnostic>> {
+    let options =
+        PdfExportOptions::try_from(pdf_options).map_err(|err| vec![Diagnostic::error(err)])?;
+    let (content, source_map) =
+        tyx_converters::try_serialized_tyx_to_typst_with_source_map(document)
+            .map_err(|err| vec![Diagnostic::from(err)])?;
+    let attribute = |diagnostics: Vec<Diagnostic>| {
+        diagnostics
+            .into_iter()
+            .map(|diagnostic| diagnostic.attribute(&source_map))
+            .collect::<Vec<_>>()
     };
 
-    let mut f = File::create(&pdf_file).unwrap();
-    f.write_all(&pdf).unwrap();
+    server
+        .compile(filename, &content, root_path, font_paths)
+        .and_then(|compiled| pdf::compiled_to_pdf(compiled, &options))
+        .map(|(pdf, warnings)| (pdf, attribute(warnings)))
+        .map_err(attribute)
+}
 
-    if open {
-        let _ = open::that(String::from("file://") + &pdf_file);
-    }
+/// Compiles the document in its session and renders the pages which changed
+/// since its previous live preview, or all of them if `full`, off the main
+/// thread, emitting them with the `pages` event, and returning the errors and
+/// warnings.
+#[tauri::command]
+pub(crate) async fn livepreview(
+    handle: tauri::AppHandle,
+    filename: String,
+    document: String,
+    root: String,
+    font_paths: Vec<String>,
+    full: bool,
+) -> Vec<Diagnostic> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let (filename, root_path) = compiled_paths(&handle, &filename, &root);
+        let result = tyx_converters::try_serialized_tyx_to_typst_with_source_map(&document)
+            .map_err(|err| vec![Diagnostic::from(err)])
+            .and_then(|(content, source_map)| {
+                let attributed = source_map.clone();
+                let attribute = |diagnostics: Vec<Diagnostic>| {
+                    diagnostics
+                        .into_iter()
+                        .map(|diagnostic| diagnostic.attribute(&attributed))
+                        .collect::<Vec<_>>()
+                };
+                handle
+                    .state::<Server>()
+                    .preview(&filename, &content, source_map, root_path, font_paths, full)
+                    .map(|(pages, warnings)| (pages, attribute(warnings)))
+                    .map_err(attribute)
+            });
+        match result {
+            Ok((pages, warnings)) => {
+                handle.emit("pages", pages).unwrap();
+                warnings
+            }
+            Err(diagnostics) => diagnostics,
+        }
+    })
+    .await
+    .unwrap_or_else(|err| vec![Diagnostic::error(err.to_string())])
+}
 
-    String::new()
+/// Returns the path of the TyX node at the given point, in points from the top
+/// left corner, of a page of the document's last live preview.
+#[tauri::command]
+pub(crate) fn jumptosource(
+    server: tauri::State<'_, Server>,
+    filename: &str,
+    page: usize,
+    x: f64,
+    y: f64,
+) -> Option<String> {
+    server.jump(filename, page, x, y)
+}
+
+/// Returns the font families available to the document, from the TyX fonts,
+/// the system fonts and its font paths.
+#[tauri::command]
+pub(crate) fn listfonts(
+    handle: tauri::AppHandle,
+    server: tauri::State<'_, Server>,
+    filename: &str,
+    font_paths: Vec<String>,
+) -> Result<Vec<FontFamily>, Vec<Diagnostic>> {
+    let (_, dirname) = compiled_paths(&handle, filename, "");
+    fonts::list_fonts(&dirname, &font_paths, server.fonts())
+}
+
+/// Drops the compile session of a closed document.
+#[tauri::command]
+pub(crate) fn closesession(
+    handle: tauri::AppHandle,
+    server: tauri::State<'_, Server>,
+    filename: &str,
+) {
+    server.close(&compiled_paths(&handle, filename, "").0);
 }
 
 #[tauri::command]
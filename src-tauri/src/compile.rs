@@ -0,0 +1,330 @@
+use std::{
+    collections::{HashMap, hash_map::Entry},
+    fs::create_dir_all,
+    io,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use tinymist_project::{
+    CompileFontArgs, CompileOnceArgs, EntryReader, LspUniverse, LspWorld, TaskInputs,
+    WorldProvider,
+    base::{EntryState, ShadowApi},
+};
+use typst::{
+    World, WorldExt,
+    diag::SourceDiagnostic,
+    layout::{PageRanges, PagedDocument},
+    syntax::{FileId, Span},
+    text::FontBook,
+};
+
+use crate::diagnostics::Diagnostic;
+
+/// A compiled document, along with the world it was compiled in, which is
+/// needed to resolve the diagnostics of later export steps.
+pub(crate) struct Compiled {
+    world: LspWorld,
+    main: FileId,
+    /// The laid out document.
+    pub(crate) document: PagedDocument,
+    /// The warnings emitted while compiling.
+    pub(crate) warnings: Vec<Diagnostic>,
+}
+
+impl Compiled {
+    /// Returns the byte offset in the main file of the given span, if it points
+    /// into the main file.
+    pub(crate) fn main_offset(&self, span: Span) -> Option<usize> {
+        if span.id()? != self.main {
+            return None;
+        }
+        self.world.range(span).map(|range| range.start)
+    }
+
+    /// Converts errors of an export step, returning them after the warnings.
+    pub(crate) fn fail(self, errors: &[SourceDiagnostic]) -> Vec<Diagnostic> {
+        fail(&self.world, self.main, self.warnings, errors)
+    }
+}
+
+/// Converts errors, returning them after the given warnings.
+fn fail(
+    world: &LspWorld,
+    main: FileId,
+    mut warnings: Vec<Diagnostic>,
+    errors: &[SourceDiagnostic],
+) -> Vec<Diagnostic> {
+    warnings.extend(
+        errors
+            .iter()
+            .map(|error| Diagnostic::from_typst(world, main, error)),
+    );
+    warnings
+}
+
+/// Compiler universes by the font paths they load, so that compiling many
+/// documents searches and loads the fonts only once.
+#[derive(Default)]
+pub(crate) struct FontCache {
+    universes: Mutex<HashMap<Vec<PathBuf>, LspUniverse>>,
+}
+
+impl FontCache {
+    /// Returns a world compiling the given main file in the root directory,
+    /// loading the fonts unless a previous world already did.
+    fn world(
+        &self,
+        root_path: &Path,
+        filename_path: &Path,
+        font_paths: Vec<PathBuf>,
+    ) -> Result<LspWorld, Vec<Diagnostic>> {
+        let Ok(entry) = EntryState::new_rooted(root_path.into(), None)
+            .try_select_path_in_workspace(filename_path)
+        else {
+            return Err(vec![Diagnostic::error(
+                "Invalid root directory, couldn't select the file itself!",
+            )]);
+        };
+
+        self.with_universe(root_path, font_paths, |universe| {
+            universe.snapshot_with(Some(TaskInputs {
+                entry,
+                ..TaskInputs::default()
+            }))
+        })
+    }
+
+    /// Calls the function with the universe loading the given font paths,
+    /// creating it in the root directory unless a previous call already did.
+    fn with_universe<T>(
+        &self,
+        root_path: &Path,
+        font_paths: Vec<PathBuf>,
+        f: impl FnOnce(&LspUniverse) -> T,
+    ) -> Result<T, Vec<Diagnostic>> {
+        let mut universes = self.universes.lock().unwrap();
+        let universe = match universes.entry(font_paths) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let universe = CompileOnceArgs {
+                    root: Some(root_path.to_path_buf()),
+                    font: CompileFontArgs {
+                        font_paths: entry.key().clone(),
+                        ..CompileFontArgs::default()
+                    },
+                    ..CompileOnceArgs::default()
+                }
+                .resolve()
+                .map_err(|err| {
+                    vec![Diagnostic::error(format!("Couldn't load the fonts: {err}"))]
+                })?;
+                entry.insert(universe)
+            }
+        };
+        Ok(f(universe))
+    }
+
+    /// Forgets the files the universes read, so that the next compilations see
+    /// the changes on disk, and drops the universes loading fonts from the
+    /// changed paths, so that their fonts are loaded again.
+    pub(crate) fn reset(&self, changed: &[PathBuf]) {
+        let mut universes = self.universes.lock().unwrap();
+        universes.retain(|font_paths, _| {
+            !changed.iter().any(|path| {
+                font_paths
+                    .iter()
+                    .any(|font_path| path.starts_with(font_path))
+            })
+        });
+        for universe in universes.values_mut() {
+            universe.reset();
+        }
+    }
+}
+
+/// Canonicalizes the path of a file which may not exist, by canonicalizing its
+/// directory.
+fn canonicalize_file(path: &Path) -> io::Result<PathBuf> {
+    let Some(name) = path.file_name() else {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "not a file"));
+    };
+    let dirname = path
+        .parent()
+        .filter(|dirname| !dirname.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    Ok(dunce::canonicalize(dirname)?.join(name))
+}
+
+/// Returns the TyX fonts directory followed by the given font paths, relative
+/// to the given directory.
+fn resolve_font_paths(
+    dirname: &Path,
+    font_paths: &[String],
+) -> Result<Vec<PathBuf>, Vec<Diagnostic>> {
+    let mut font_paths = font_paths
+        .iter()
+        .map(|path| {
+            let path = dirname.join(path);
+            dunce::canonicalize(&path).map_err(|err| {
+                vec![Diagnostic::error(format!(
+                    "Invalid font path {}: {err}",
+                    path.display()
+                ))]
+            })
+        })
+        .collect::<Result<Vec<PathBuf>, _>>()?;
+
+    let tyx_fonts_path = crate::utils::get_tyx_config_dir().join("fonts");
+    if !tyx_fonts_path.is_dir() {
+        create_dir_all(&tyx_fonts_path).unwrap();
+    }
+    font_paths.insert(0, tyx_fonts_path);
+    Ok(font_paths)
+}
+
+/// Calls the function with the book of the TyX fonts, the system fonts and the
+/// given font paths, relative to the given directory.
+pub(crate) fn with_font_book<T>(
+    dirname: &Path,
+    font_paths: &[String],
+    fonts: &FontCache,
+    f: impl FnOnce(&FontBook) -> T,
+) -> Result<T, Vec<Diagnostic>> {
+    let font_paths = resolve_font_paths(dirname, font_paths)?;
+    fonts.with_universe(dirname, font_paths, |universe| {
+        f(universe.snapshot().book())
+    })
+}
+
+/// Returns a world whose main file is `filename`, loading the TyX fonts and the
+/// given font paths, relative to the directory of the file. The file itself may
+/// not exist, e.g. when its content is shadowed.
+pub(crate) fn world(
+    filename: &str,
+    root_path: PathBuf,
+    font_paths: Vec<String>,
+    fonts: &FontCache,
+) -> Result<LspWorld, Vec<Diagnostic>> {
+    let dirname = Path::new(filename).parent().unwrap_or(Path::new(""));
+    let font_paths = resolve_font_paths(dirname, &font_paths)?;
+    let filename_path = canonicalize_file(Path::new(filename)).map_err(|err| {
+        vec![Diagnostic::error(format!(
+            "Invalid file path {filename}: {err}"
+        ))]
+    })?;
+    let root_path = dunce::canonicalize(&root_path).map_err(|err| {
+        vec![Diagnostic::error(format!(
+            "Invalid root directory {}: {err}",
+            root_path.display()
+        ))]
+    })?;
+    fonts.world(&root_path, &filename_path, font_paths)
+}
+
+/// A world kept between compilations of a document, so that only its main
+/// file changes and Typst reuses the rest of its work.
+pub(crate) struct Session {
+    world: LspWorld,
+    main: FileId,
+    root_path: PathBuf,
+    font_paths: Vec<String>,
+}
+
+impl Session {
+    /// Creates a session compiling `filename` in the root directory with the
+    /// given font paths.
+    pub(crate) fn new(
+        filename: &str,
+        root_path: PathBuf,
+        font_paths: Vec<String>,
+        fonts: &FontCache,
+    ) -> Result<Self, Vec<Diagnostic>> {
+        let world = world(filename, root_path.clone(), font_paths.clone(), fonts)?;
+        let main = world.main_id().unwrap();
+        Ok(Self {
+            world,
+            main,
+            root_path,
+            font_paths,
+        })
+    }
+
+    /// Whether the session compiles in the given root directory with the given
+    /// font paths.
+    pub(crate) fn uses(&self, root_path: &Path, font_paths: &[String]) -> bool {
+        self.root_path == root_path && self.font_paths == font_paths
+    }
+
+    /// Compiles the given Typst content as the new contents of the main file,
+    /// returning the document along with any warnings, or all the errors and
+    /// warnings if compilation failed.
+    pub(crate) fn compile(&mut self, content: &str) -> Result<Compiled, Vec<Diagnostic>> {
+        self.world
+            .map_shadow_by_id(
+                self.main,
+                typst::foundations::Bytes::from_string(content.to_owned()),
+            )
+            .map_err(|err| {
+                vec![Diagnostic::error(format!(
+                    "Couldn't update the document: {err}"
+                ))]
+            })?;
+        let world = self.world.clone();
+        let main = self.main;
+        let compiled = typst::compile::<PagedDocument>(&world);
+        let warnings = compiled
+            .warnings
+            .iter()
+            .map(|warning| Diagnostic::from_typst(&world, main, warning))
+            .collect::<Vec<_>>();
+        match compiled.output {
+            Ok(document) => Ok(Compiled {
+                world,
+                main,
+                document,
+                warnings,
+            }),
+            Err(errors) => Err(fail(&world, main, warnings, &errors)),
+        }
+    }
+}
+
+/// Compiles the given Typst content as if it were the contents of `filename`,
+/// returning the document along with any warnings, or all the errors and
+/// warnings if compilation failed. Worlds loading the same fonts share them
+/// through the font cache.
+pub(crate) fn compile(
+    filename: &str,
+    content: &str,
+    root_path: PathBuf,
+    font_paths: Vec<String>,
+    fonts: &FontCache,
+) -> Result<Compiled, Vec<Diagnostic>> {
+    Session::new(filename, root_path, font_paths, fonts)?.compile(content)
+}
+
+/// Parses page ranges such as `1-3,5,8-`.
+pub(crate) fn parse_page_ranges(value: &str) -> Result<PageRanges, String> {
+    let page = |page: &str| {
+        let page = page.trim();
+        if page.is_empty() {
+            return Ok(None);
+        }
+        page.parse::<NonZeroUsize>()
+            .map(Some)
+            .map_err(|_| format!("invalid page number {page:?}"))
+    };
+    value
+        .split(',')
+        .map(|range| match range.split_once('-') {
+            Some((start, end)) => Ok(page(start)?..=page(end)?),
+            None => {
+                let page = page(range)?.ok_or("empty page range")?;
+                Ok(Some(page)..=Some(page))
+            }
+        })
+        .collect::<Result<Vec<_>, String>>()
+        .map(PageRanges::new)
+}
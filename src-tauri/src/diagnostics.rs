@@ -0,0 +1,186 @@
+use std::{fmt, ops::Range};
+
+use serde::Serialize;
+use typst::{
+    World, WorldExt,
+    diag::{self, SourceDiagnostic},
+    syntax::{FileId, Span},
+};
+use tyx_converters::{ConversionError, SourceMap};
+use tyx_schema::{Issue, Severity};
+
+/// The severity of a diagnostic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum DiagnosticSeverity {
+    Error,
+    Warning,
+}
+
+/// A location in a Typst file, with 1-based line and column numbers.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Location {
+    /// The file, or `None` for the generated main file.
+    pub(crate) file: Option<String>,
+    pub(crate) range: Range<usize>,
+    pub(crate) line: usize,
+    pub(crate) column: usize,
+}
+
+/// A step in the trace of a diagnostic, e.g. a function call.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TracePoint {
+    pub(crate) message: String,
+    pub(crate) location: Option<Location>,
+}
+
+/// A serializable error or warning reported while converting or compiling a document.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Diagnostic {
+    pub(crate) severity: DiagnosticSeverity,
+    pub(crate) message: String,
+    pub(crate) hints: Vec<String>,
+    pub(crate) trace: Vec<TracePoint>,
+    pub(crate) location: Option<Location>,
+    /// The path of the TyX node the diagnostic originates from.
+    pub(crate) path: Option<String>,
+}
+
+/// Returns the 1-based line and column of the given byte offset in the text.
+fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+/// Resolves the location of a span, if it points into some file of the world.
+fn locate(world: &dyn World, main: FileId, span: Span) -> Option<Location> {
+    let id = span.id()?;
+    let range = world.range(span)?;
+    let source = world.source(id).ok()?;
+    let (line, column) = line_column(source.text(), range.start);
+    Some(Location {
+        file: (id != main).then(|| id.vpath().as_rooted_path().display().to_string()),
+        range,
+        line,
+        column,
+    })
+}
+
+impl Diagnostic {
+    /// Creates an error without any location.
+    pub(crate) fn error(message: impl Into<String>) -> Self {
+        Self {
+            severity: DiagnosticSeverity::Error,
+            message: message.into(),
+            hints: Vec::new(),
+            trace: Vec::new(),
+            location: None,
+            path: None,
+        }
+    }
+
+    /// Converts a Typst diagnostic, resolving its spans in the given world.
+    pub(crate) fn from_typst(
+        world: &dyn World,
+        main: FileId,
+        diagnostic: &SourceDiagnostic,
+    ) -> Self {
+        Self {
+            severity: match diagnostic.severity {
+                diag::Severity::Error => DiagnosticSeverity::Error,
+                diag::Severity::Warning => DiagnosticSeverity::Warning,
+            },
+            message: diagnostic.message.to_string(),
+            hints: diagnostic
+                .hints
+                .iter()
+                .map(|hint| hint.to_string())
+                .collect(),
+            trace: diagnostic
+                .trace
+                .iter()
+                .map(|point| TracePoint {
+                    message: point.v.to_string(),
+                    location: locate(world, main, point.span),
+                })
+                .collect(),
+            location: locate(world, main, diagnostic.span),
+            path: None,
+        }
+    }
+
+    /// Attributes the diagnostic to the TyX node its location was generated from.
+    pub(crate) fn attribute(mut self, source_map: &SourceMap) -> Self {
+        if let Some(location) = &self.location
+            && location.file.is_none()
+        {
+            self.path = source_map.path_of(location.range.clone()).map(String::from);
+        }
+        self
+    }
+}
+
+impl From<ConversionError> for Diagnostic {
+    fn from(error: ConversionError) -> Self {
+        let path = error.path().to_string();
+        Self {
+            path: (!path.is_empty()).then_some(path),
+            ..Self::error(error.to_string())
+        }
+    }
+}
+
+impl From<Issue> for Diagnostic {
+    fn from(issue: Issue) -> Self {
+        Self {
+            severity: match issue.severity {
+                Severity::Error => DiagnosticSeverity::Error,
+                Severity::Warning => DiagnosticSeverity::Warning,
+            },
+            path: Some(issue.path),
+            ..Self::error(issue.message)
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{file}:")?;
+        }
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            DiagnosticSeverity::Error => "error",
+            DiagnosticSeverity::Warning => "warning",
+        };
+        write!(f, "{severity}: {}", self.message)?;
+        match (&self.location, &self.path) {
+            (Some(location), Some(path)) => write!(f, "\n  --> {location} ({path})")?,
+            (Some(location), None) => write!(f, "\n  --> {location}")?,
+            (None, Some(path)) => write!(f, "\n  --> {path}")?,
+            (None, None) => {}
+        }
+        for point in &self.trace {
+            write!(f, "\n  = {}", point.message)?;
+            if let Some(location) = &point.location {
+                write!(f, " at {location}")?;
+            }
+        }
+        for hint in &self.hints {
+            write!(f, "\n  = hint: {hint}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,89 @@
+use std::path::Path;
+
+use serde::Serialize;
+use typst::text::{FontBook, FontInfo, FontStyle};
+
+use crate::compile::{FontCache, with_font_book};
+use crate::diagnostics::Diagnostic;
+
+/// A font family, as the Typst compiler sees it.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FontFamily {
+    /// The name of the family, as given to `text(font: ...)`.
+    pub(crate) name: String,
+    /// The variants of the family, e.g. its bold and italic fonts.
+    pub(crate) variants: Vec<FontVariant>,
+}
+
+/// A font of a family.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FontVariant {
+    /// `normal`, `italic` or `oblique`.
+    pub(crate) style: String,
+    /// The weight, from 100 (thin) to 900 (black), 400 being regular.
+    pub(crate) weight: u16,
+    /// The width relative to the normal width, from 0.5 to 2.
+    pub(crate) stretch: f64,
+    /// The ranges of Unicode code points the font covers, both ends included.
+    pub(crate) coverage: Vec<(u32, u32)>,
+}
+
+impl From<&FontInfo> for FontVariant {
+    fn from(info: &FontInfo) -> Self {
+        let mut coverage: Vec<(u32, u32)> = Vec::new();
+        for codepoint in info.coverage.iter() {
+            match coverage.last_mut() {
+                Some((_, end)) if *end + 1 == codepoint => *end = codepoint,
+                _ => coverage.push((codepoint, codepoint)),
+            }
+        }
+
+        Self {
+            style: match info.variant.style {
+                FontStyle::Normal => "normal",
+                FontStyle::Italic => "italic",
+                FontStyle::Oblique => "oblique",
+            }
+            .into(),
+            weight: info.variant.weight.to_number(),
+            stretch: info.variant.stretch.to_ratio().get(),
+            coverage,
+        }
+    }
+}
+
+impl FontVariant {
+    /// The number of code points the font covers.
+    pub(crate) fn codepoints(&self) -> u32 {
+        self.coverage
+            .iter()
+            .map(|(start, end)| end - start + 1)
+            .sum()
+    }
+}
+
+/// Returns the families of a font book, sorted by name.
+fn families(book: &FontBook) -> Vec<FontFamily> {
+    let mut families = book
+        .families()
+        .map(|(name, infos)| FontFamily {
+            name: name.to_string(),
+            variants: infos.map(FontVariant::from).collect(),
+        })
+        .collect::<Vec<_>>();
+    families.sort_by_key(|family| family.name.to_lowercase());
+    families
+}
+
+/// Returns the font families available to documents in the given directory:
+/// the TyX fonts, the system fonts and the given font paths, relative to the
+/// directory.
+pub(crate) fn list_fonts(
+    dirname: &Path,
+    font_paths: &[String],
+    fonts: &FontCache,
+) -> Result<Vec<FontFamily>, Vec<Diagnostic>> {
+    with_font_book(dirname, font_paths, fonts, families)
+}
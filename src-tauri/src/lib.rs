@@ -7,8 +7,14 @@ use tauri::{
 
 mod cli;
 mod cmds;
+mod compile;
+mod diagnostics;
+mod fonts;
 mod pdf;
+mod render;
+mod server;
 mod utils;
+mod watch;
 
 pub fn get_menu(handle: &tauri::AppHandle) -> Result<Menu<Wry>, tauri::Error> {
     let app_submenu = SubmenuBuilder::new(handle, "App")
@@ -53,11 +59,16 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_process::init())
         .plugin(tauri_plugin_dialog::init())
+        .manage(server::Server::default())
         .invoke_handler(tauri::generate_handler![
             cmds::open,
             cmds::save,
             cmds::saveas,
             cmds::preview,
+            cmds::closesession,
+            cmds::livepreview,
+            cmds::jumptosource,
+            cmds::listfonts,
             cmds::insertimage,
             cmds::readimage
         ])
@@ -71,8 +82,8 @@ pub fn run() {
 
     let args = cli::Args::parse();
 
-    if args.process() {
-        return;
+    if let Some(code) = args.process() {
+        std::process::exit(code);
     }
 
     let mut files: Vec<PathBuf> = Vec::new();
@@ -106,11 +117,16 @@ pub fn run() {
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_dialog::init())
         .menu(get_menu)
+        .manage(server::Server::default())
         .invoke_handler(tauri::generate_handler![
             cmds::open,
             cmds::save,
             cmds::saveas,
             cmds::preview,
+            cmds::closesession,
+            cmds::livepreview,
+            cmds::jumptosource,
+            cmds::listfonts,
             cmds::insertimage,
             cmds::readimage,
             cmds::getsettings,
@@ -1,76 +1,113 @@
-use std::{
-    fs::{File, create_dir_all},
-    path::{Path, PathBuf},
-};
+use std::path::PathBuf;
 
-use tinymist_project::{
-    CompileFontArgs, CompileOnceArgs, EntryReader, TaskInputs, WorldProvider, base::ShadowApi,
+use chrono::{Datelike, Timelike};
+use typst::{
+    foundations::{Datetime, Smart},
+    layout::PageRanges,
 };
-use typst_pdf::PdfOptions;
+use typst_pdf::{PdfOptions, PdfStandard, PdfStandards, Timestamp};
+use tyx_schema::TyXPdfOptions;
 
-pub(crate) fn typst_to_pdf(
-    filename: &str,
-    content: &str,
-    root_path: PathBuf,
-    font_paths: Vec<String>,
-) -> Result<Vec<u8>, String> {
-    let dirname = Path::new(filename).parent().unwrap();
-    let mut font_paths = font_paths
-        .iter()
-        .map(|path| {
-            let mut p = PathBuf::from(dirname);
-            p.push(PathBuf::from(path));
-            dunce::canonicalize(&p).unwrap()
-        })
-        .collect::<Vec<PathBuf>>();
+use crate::compile::{Compiled, FontCache, compile, parse_page_ranges};
+use crate::diagnostics::Diagnostic;
 
-    let tyx_fonts_path = crate::utils::get_tyx_config_dir().join("fonts");
-    if !tyx_fonts_path.is_dir() {
-        create_dir_all(&tyx_fonts_path).unwrap();
-    }
-    font_paths.insert(0, tyx_fonts_path);
+/// Options of the PDF export, which default to those of Typst.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PdfExportOptions {
+    /// The standards the PDF must conform to.
+    pub(crate) standards: Vec<PdfStandard>,
+    /// The pages to export, all of them by default.
+    pub(crate) pages: Option<PageRanges>,
+    /// The creation time of the document, in seconds since 1970.
+    pub(crate) creation_timestamp: Option<i64>,
+    /// A stable identifier of the document, derived from its title by default.
+    pub(crate) identifier: Option<String>,
+}
 
-    let filename_path = PathBuf::from(&filename);
-    if !filename_path.is_file() {
-        File::create(&filename_path).unwrap();
-    }
-    let filename_path = dunce::canonicalize(filename_path).unwrap();
-    let universe = CompileOnceArgs {
-        root: Some(dunce::canonicalize(&root_path).unwrap()),
-        input: Some(filename_path.to_str().unwrap().to_string()),
-        font: CompileFontArgs {
-            font_paths,
-            ..CompileFontArgs::default()
-        },
-        ..CompileOnceArgs::default()
+impl TryFrom<&TyXPdfOptions> for PdfExportOptions {
+    type Error = String;
+
+    fn try_from(options: &TyXPdfOptions) -> Result<Self, Self::Error> {
+        Ok(Self {
+            standards: options
+                .standards
+                .iter()
+                .map(|standard| parse_pdf_standard(standard))
+                .collect::<Result<_, _>>()?,
+            pages: options
+                .pages
+                .as_deref()
+                .map(parse_page_ranges)
+                .transpose()?,
+            creation_timestamp: options.creation_timestamp.map(|seconds| seconds as i64),
+            identifier: options.identifier.clone(),
+        })
     }
-    .resolve()
-    .unwrap();
-    let entry = match universe
-        .entry_state()
-        .try_select_path_in_workspace(&filename_path)
-    {
-        Ok(entry) => entry,
-        Err(_e) => {
-            return Err("Invalid root directory, couldn't select the file itself!".into());
-        }
+}
+
+/// Parses a PDF standard such as `a-2b` or `ua-1`.
+pub(crate) fn parse_pdf_standard(value: &str) -> Result<PdfStandard, String> {
+    serde_json::from_value(serde_json::Value::String(value.into()))
+        .map_err(|_| format!("unknown PDF standard {value:?}"))
+}
+
+/// Converts seconds since 1970 to a PDF timestamp.
+fn timestamp(seconds: i64) -> Option<Timestamp> {
+    let time = chrono::DateTime::from_timestamp(seconds, 0)?;
+    Datetime::from_ymd_hms(
+        time.year(),
+        time.month().try_into().ok()?,
+        time.day().try_into().ok()?,
+        time.hour().try_into().ok()?,
+        time.minute().try_into().ok()?,
+        time.second().try_into().ok()?,
+    )
+    .map(Timestamp::new_utc)
+}
+
+/// Exports a compiled document to a PDF, returning it along with the warnings,
+/// or all the errors and warnings if the export failed.
+pub(crate) fn compiled_to_pdf(
+    compiled: Compiled,
+    options: &PdfExportOptions,
+) -> Result<(Vec<u8>, Vec<Diagnostic>), Vec<Diagnostic>> {
+    let standards = PdfStandards::new(&options.standards)
+        .map_err(|err| vec![Diagnostic::error(err.to_string())])?;
+    let timestamp = match options.creation_timestamp {
+        Some(seconds) => Some(timestamp(seconds).ok_or_else(|| {
+            vec![Diagnostic::error(format!(
+                "Invalid creation timestamp {seconds}"
+            ))]
+        })?),
+        None => None,
     };
-    let mut world = universe.snapshot_with(Some(TaskInputs {
-        entry,
-        ..TaskInputs::default()
-    }));
-    let main = world.main_id().unwrap();
-    let _ = world.map_shadow_by_id(
-        main,
-        typst::foundations::Bytes::from_string(content.to_owned()),
-    );
-    let doc = match typst::compile(&world).output {
-        Ok(doc) => doc,
-        Err(e) => {
-            return Err(e[0].message.to_string());
-        }
+
+    let options = PdfOptions {
+        ident: options
+            .identifier
+            .as_deref()
+            .map_or(Smart::Auto, Smart::Custom),
+        timestamp,
+        page_ranges: options.pages.clone(),
+        standards,
+        ..PdfOptions::default()
     };
-    let pdf = typst_pdf::pdf(&doc, &PdfOptions::default()).unwrap();
+    match typst_pdf::pdf(&compiled.document, &options) {
+        Ok(pdf) => Ok((pdf, compiled.warnings)),
+        Err(errors) => Err(compiled.fail(&errors)),
+    }
+}
 
-    Ok(pdf)
+/// Compiles the given Typst content to a PDF, returning it along with any
+/// warnings, or all the errors and warnings if compilation failed.
+pub(crate) fn typst_to_pdf(
+    filename: &str,
+    content: &str,
+    root_path: PathBuf,
+    font_paths: Vec<String>,
+    fonts: &FontCache,
+    options: &PdfExportOptions,
+) -> Result<(Vec<u8>, Vec<Diagnostic>), Vec<Diagnostic>> {
+    let compiled = compile(filename, content, root_path, font_paths, fonts)?;
+    compiled_to_pdf(compiled, options)
 }
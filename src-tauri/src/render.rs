@@ -0,0 +1,121 @@
+use std::{num::NonZeroUsize, path::PathBuf};
+
+use typst::{
+    layout::{Frame, FrameItem, Page, PageRanges, PagedDocument, Point, Size},
+    syntax::Span,
+};
+
+use crate::compile::{FontCache, compile};
+use crate::diagnostics::Diagnostic;
+
+/// Rendered pages, along with their (1-based) page numbers.
+pub(crate) type Pages = Vec<(usize, Vec<u8>)>;
+
+/// Returns the pages of the document in the given ranges, or all of them.
+fn selected_pages<'a>(
+    document: &'a PagedDocument,
+    pages: Option<&'a PageRanges>,
+) -> impl Iterator<Item = (usize, &'a Page)> {
+    document
+        .pages
+        .iter()
+        .enumerate()
+        .map(|(index, page)| (index + 1, page))
+        .filter(move |(number, _)| {
+            pages.is_none_or(|pages| pages.includes_page(NonZeroUsize::new(*number).unwrap()))
+        })
+}
+
+/// Compiles the given Typst content and renders its pages to PNG images at the
+/// given resolution in pixels per inch, returning them along with any warnings,
+/// or all the errors and warnings if compilation failed.
+pub(crate) fn typst_to_png(
+    filename: &str,
+    content: &str,
+    root_path: PathBuf,
+    font_paths: Vec<String>,
+    fonts: &FontCache,
+    ppi: f32,
+    pages: Option<&PageRanges>,
+) -> Result<(Pages, Vec<Diagnostic>), Vec<Diagnostic>> {
+    let compiled = compile(filename, content, root_path, font_paths, fonts)?;
+    let mut images = Vec::new();
+    for (number, page) in selected_pages(&compiled.document, pages) {
+        let pixmap = typst_render::render(page, ppi / 72.0);
+        match pixmap.encode_png() {
+            Ok(png) => images.push((number, png)),
+            Err(err) => {
+                let mut diagnostics = compiled.warnings;
+                diagnostics.push(Diagnostic::error(format!(
+                    "Couldn't encode page {number} as PNG: {err}"
+                )));
+                return Err(diagnostics);
+            }
+        }
+    }
+
+    Ok((images, compiled.warnings))
+}
+
+/// Compiles the given Typst content and renders its pages to SVG images,
+/// returning them along with any warnings, or all the errors and warnings if
+/// compilation failed.
+pub(crate) fn typst_to_svg(
+    filename: &str,
+    content: &str,
+    root_path: PathBuf,
+    font_paths: Vec<String>,
+    fonts: &FontCache,
+    pages: Option<&PageRanges>,
+) -> Result<(Pages, Vec<Diagnostic>), Vec<Diagnostic>> {
+    let compiled = compile(filename, content, root_path, font_paths, fonts)?;
+    let images = selected_pages(&compiled.document, pages)
+        .map(|(number, page)| (number, typst_svg::svg(page).into_bytes()))
+        .collect();
+
+    Ok((images, compiled.warnings))
+}
+
+/// Returns the span of the innermost text or image at the given point of a
+/// frame, which is relative to the frame's top left corner.
+pub(crate) fn span_at(frame: &Frame, click: Point) -> Option<Span> {
+    let contains = |position: Point, size: Size| {
+        position.x <= click.x
+            && click.x <= position.x + size.x
+            && position.y <= click.y
+            && click.y <= position.y + size.y
+    };
+
+    // Later items are drawn on top of earlier ones
+    for (position, item) in frame.items().rev() {
+        match item {
+            FrameItem::Group(group) => {
+                let Some(inverse) = group.transform.invert() else {
+                    continue;
+                };
+                let point = (click - *position).transform(inverse);
+                if let Some(span) = span_at(&group.frame, point) {
+                    return Some(span);
+                }
+            }
+            FrameItem::Text(text) => {
+                let mut x = position.x;
+                for glyph in &text.glyphs {
+                    let width = glyph.x_advance.at(text.size);
+                    if contains(
+                        Point::new(x, position.y - text.size),
+                        Size::new(width, text.size),
+                    ) {
+                        return Some(glyph.span.0);
+                    }
+                    x += width;
+                }
+            }
+            FrameItem::Image(_, size, span) if contains(*position, *size) => {
+                return Some(*span);
+            }
+            _ => {}
+        }
+    }
+    None
+}
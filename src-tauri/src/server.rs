@@ -0,0 +1,145 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use serde::Serialize;
+use typst::layout::{Abs, Point};
+use tyx_converters::SourceMap;
+
+use crate::compile::{Compiled, FontCache, Session};
+use crate::diagnostics::Diagnostic;
+use crate::render::span_at;
+
+/// The pages of a live preview which changed since the previous one.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PreviewPages {
+    /// The filename the document is compiled as.
+    pub(crate) filename: String,
+    /// The number of pages of the document.
+    pub(crate) count: usize,
+    /// The changed pages as SVG images, along with their (0-based) indices.
+    pub(crate) pages: Vec<(usize, String)>,
+}
+
+/// The last live preview of a document, to find out which pages changed and
+/// where clicks on them originate from.
+struct Preview {
+    compiled: Compiled,
+    source_map: SourceMap,
+    hashes: Vec<u128>,
+}
+
+/// The compile sessions of the open documents, by their filenames, which keep
+/// their worlds warm between previews.
+#[derive(Default)]
+pub(crate) struct Server {
+    fonts: FontCache,
+    sessions: Mutex<HashMap<String, Arc<Mutex<Session>>>>,
+    previews: Mutex<HashMap<String, Preview>>,
+}
+
+impl Server {
+    /// Compiles the given Typst content of the document, in its session unless
+    /// the root directory or the font paths changed since the last compilation.
+    pub(crate) fn compile(
+        &self,
+        filename: &str,
+        content: &str,
+        root_path: PathBuf,
+        font_paths: Vec<String>,
+    ) -> Result<Compiled, Vec<Diagnostic>> {
+        // The map is only locked to look sessions up and insert them, so that
+        // compiling a document or loading its fonts doesn't hold up the others
+        let session = self.sessions.lock().unwrap().get(filename).cloned();
+        let session = match session {
+            Some(session) if session.lock().unwrap().uses(&root_path, &font_paths) => session,
+            _ => {
+                let session = Session::new(filename, root_path, font_paths, &self.fonts)?;
+                let session = Arc::new(Mutex::new(session));
+                self.sessions
+                    .lock()
+                    .unwrap()
+                    .insert(filename.to_string(), session.clone());
+                session
+            }
+        };
+        session.lock().unwrap().compile(content)
+    }
+
+    /// Compiles the given Typst content of the document and renders the pages
+    /// which changed since its previous preview to SVG, or all of them if
+    /// `full`, returning them along with any warnings, or all the errors and
+    /// warnings if compilation failed.
+    pub(crate) fn preview(
+        &self,
+        filename: &str,
+        content: &str,
+        source_map: SourceMap,
+        root_path: PathBuf,
+        font_paths: Vec<String>,
+        full: bool,
+    ) -> Result<(PreviewPages, Vec<Diagnostic>), Vec<Diagnostic>> {
+        let compiled = self.compile(filename, content, root_path, font_paths)?;
+        let warnings = compiled.warnings.clone();
+        let hashes = compiled
+            .document
+            .pages
+            .iter()
+            .map(typst::utils::hash128)
+            .collect::<Vec<_>>();
+
+        let mut previews = self.previews.lock().unwrap();
+        let previous = previews
+            .get(filename)
+            .filter(|_| !full)
+            .map(|preview| preview.hashes.as_slice())
+            .unwrap_or_default();
+        let pages = compiled
+            .document
+            .pages
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| previous.get(*index) != hashes.get(*index))
+            .map(|(index, page)| (index, typst_svg::svg(page)))
+            .collect();
+        let pages = PreviewPages {
+            filename: filename.to_string(),
+            count: hashes.len(),
+            pages,
+        };
+        previews.insert(
+            filename.to_string(),
+            Preview {
+                compiled,
+                source_map,
+                hashes,
+            },
+        );
+        Ok((pages, warnings))
+    }
+
+    /// Returns the path of the TyX node at the given point, in points from the
+    /// top left corner, of a page of the document's last preview.
+    pub(crate) fn jump(&self, filename: &str, page: usize, x: f64, y: f64) -> Option<String> {
+        let previews = self.previews.lock().unwrap();
+        let preview = previews.get(filename)?;
+        let frame = &preview.compiled.document.pages.get(page)?.frame;
+        let span = span_at(frame, Point::new(Abs::pt(x), Abs::pt(y)))?;
+        let offset = preview.compiled.main_offset(span)?;
+        preview.source_map.path_at(offset).map(String::from)
+    }
+
+    /// The fonts shared by the sessions.
+    pub(crate) fn fonts(&self) -> &FontCache {
+        &self.fonts
+    }
+
+    /// Drops the session and the preview of a closed document.
+    pub(crate) fn close(&self, filename: &str) {
+        self.sessions.lock().unwrap().remove(filename);
+        self.previews.lock().unwrap().remove(filename);
+    }
+}
@@ -0,0 +1,135 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{self, RecvTimeoutError},
+    time::Duration,
+};
+
+use notify::{EventKind, RecursiveMode, Watcher};
+
+/// How long to wait for more changes before reporting them, since editors often
+/// save files in several steps.
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Returns the sources of the images referenced by the serialized TyX document.
+pub(crate) fn image_sources(document: &str) -> Vec<String> {
+    fn collect(value: &serde_json::Value, sources: &mut Vec<String>) {
+        match value {
+            serde_json::Value::Object(object) => {
+                if object.get("type").and_then(|kind| kind.as_str()) == Some("image")
+                    && let Some(src) = object.get("src").and_then(|src| src.as_str())
+                {
+                    sources.push(src.to_string());
+                }
+                object.values().for_each(|value| collect(value, sources));
+            }
+            serde_json::Value::Array(array) => {
+                array.iter().for_each(|value| collect(value, sources));
+            }
+            _ => {}
+        }
+    }
+
+    let mut sources = Vec::new();
+    if let Ok(document) = serde_json::from_str(document) {
+        collect(&document, &mut sources);
+    }
+    sources
+}
+
+/// Watches the given paths, recursively for directories, calling `on_change`
+/// with the changed paths after each batch of changes, which returns the paths
+/// to watch from then on. Returns when Ctrl+C is pressed, or if watching fails.
+pub(crate) fn watch(
+    mut paths: Vec<PathBuf>,
+    mut on_change: impl FnMut(&[PathBuf]) -> Vec<PathBuf>,
+) -> notify::Result<()> {
+    // The events of the watcher, or `None` when Ctrl+C is pressed
+    let (sender, receiver) = mpsc::channel();
+    let stop = sender.clone();
+    ctrlc::set_handler(move || {
+        let _ = stop.send(None);
+    })
+    .map_err(|err| notify::Error::generic(&format!("failed to handle Ctrl+C: {err}")))?;
+    let mut watcher = notify::recommended_watcher(move |event| {
+        let _ = sender.send(Some(event));
+    })?;
+    for path in &paths {
+        watch_path(&mut watcher, path)?;
+    }
+
+    let disconnected = || notify::Error::generic("the watcher stopped");
+    loop {
+        let Some(mut next) = receiver.recv().map_err(|_| disconnected())? else {
+            return Ok(());
+        };
+        let mut changed = Vec::new();
+        loop {
+            let event = next?;
+            if matches!(
+                event.kind,
+                EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+            ) {
+                changed.extend(event.paths);
+            }
+            next = match receiver.recv_timeout(DEBOUNCE) {
+                Ok(Some(event)) => event,
+                Ok(None) => return Ok(()),
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return Err(disconnected()),
+            };
+        }
+
+        changed.sort();
+        changed.dedup();
+        if changed.is_empty() {
+            continue;
+        }
+        let next_paths = on_change(&changed);
+        for path in paths.iter().filter(|path| !next_paths.contains(path)) {
+            // The path may have been removed, which already stopped watching it
+            let _ = watcher.unwatch(path);
+        }
+        for path in next_paths.iter().filter(|path| !paths.contains(path)) {
+            watch_path(&mut watcher, path)?;
+        }
+        paths = next_paths;
+    }
+}
+
+/// Watches a path, recursively if it is a directory.
+fn watch_path(watcher: &mut impl Watcher, path: &Path) -> notify::Result<()> {
+    let mode = if path.is_dir() {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    };
+    watcher.watch(path, mode)
+}
+
+/// Returns the paths to watch for a file: the file, its root directory, its
+/// fonts and the images it references outside of the root.
+pub(crate) fn watched_paths(
+    file: &Path,
+    root: &Path,
+    font_paths: &[PathBuf],
+    contents: &str,
+) -> Vec<PathBuf> {
+    let mut paths = vec![root.to_path_buf()];
+    if !file.starts_with(root) {
+        paths.push(file.to_path_buf());
+    }
+    let fonts = crate::utils::get_tyx_config_dir().join("fonts");
+    if fonts.is_dir() {
+        paths.push(fonts);
+    }
+    paths.extend(font_paths.iter().cloned());
+    let dirname = file.parent().unwrap_or(Path::new(""));
+    for src in image_sources(contents) {
+        if let Ok(image) = dunce::canonicalize(dirname.join(src))
+            && !image.starts_with(root)
+        {
+            paths.push(image);
+        }
+    }
+    paths
+}
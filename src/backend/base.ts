@@ -9,5 +9,33 @@ export const serializeDocument = (document: TyXDocument) => {
     ...document,
     dirty: undefined,
     filename: undefined,
+    id: undefined,
   })
 }
+
+/** The pages of a live preview which changed since the previous one. */
+export interface PreviewPages {
+  /** The filename the document is compiled as. */
+  filename: string
+  /** The number of pages of the document. */
+  count: number
+  /** The changed pages as SVG images, along with their indices. */
+  pages: [number, string][]
+}
+
+/** A font of a family available to the Typst compiler. */
+export interface FontVariant {
+  style: "normal" | "italic" | "oblique"
+  /** The weight, from 100 (thin) to 900 (black), 400 being regular. */
+  weight: number
+  /** The width relative to the normal width, from 0.5 to 2. */
+  stretch: number
+  /** The ranges of Unicode code points the font covers, both ends included. */
+  coverage: [number, number][]
+}
+
+/** A font family available to the Typst compiler. */
+export interface FontFamily {
+  name: string
+  variants: FontVariant[]
+}
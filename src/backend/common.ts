@@ -2,12 +2,14 @@ import { t } from "i18next"
 import { TyXDocument } from "../models"
 import { showConfirmModal } from "../utilities"
 import { getLocalStorage, setLocalStorage } from "../utilities/hooks"
+import { closeSession } from "."
 
 const close = (
   openDocuments: TyXDocument[],
   index: number,
   currentDocument: number,
 ) => {
+  closeSession(openDocuments[index])
   openDocuments.splice(index, 1)
   setLocalStorage("Open Documents", openDocuments)
   if (index <= currentDocument && currentDocument !== 0) {
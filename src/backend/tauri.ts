@@ -10,20 +10,89 @@ import { relaunch } from "@tauri-apps/plugin-process"
 import { check } from "@tauri-apps/plugin-updater"
 import { z } from "zod/v4"
 import { executeCommandSequence } from "../commands"
-import { serialized_tyx_to_typst } from "../converters"
 import { TyXDocument, TyXSettings } from "../models"
 import { getSettings } from "../settings"
 import { showFailureMessage } from "../utilities"
 import { getLocalStorage, setLocalStorage } from "../utilities/hooks"
-import { serializeDocument, Update } from "./base"
+import {
+  FontFamily,
+  PreviewPages,
+  serializeDocument,
+  Update,
+} from "./base"
 
 let version: string
 
+/** A location in a Typst file, as reported by the backend. */
+interface DiagnosticLocation {
+  file?: string
+  line: number
+  column: number
+}
+
+/** An error or warning reported by the backend while previewing. */
+interface Diagnostic {
+  severity: "error" | "warning"
+  message: string
+  hints: string[]
+  trace: { message: string; location?: DiagnosticLocation }[]
+  location?: DiagnosticLocation
+  path?: string
+}
+
+const formatLocation = (location: DiagnosticLocation) =>
+  `${location.file ? location.file + ":" : ""}${location.line}:${location.column}`
+
+const formatDiagnostic = (diagnostic: Diagnostic) => {
+  const lines = [`${diagnostic.severity}: ${diagnostic.message}`]
+  const where = [
+    diagnostic.location && formatLocation(diagnostic.location),
+    diagnostic.path && `(${diagnostic.path})`,
+  ].filter(Boolean)
+  if (where.length > 0) {
+    lines.push(`  --> ${where.join(" ")}`)
+  }
+  for (const point of diagnostic.trace) {
+    lines.push(
+      `  = ${point.message}${point.location ? " at " + formatLocation(point.location) : ""}`,
+    )
+  }
+  for (const hint of diagnostic.hints) {
+    lines.push(`  = hint: ${hint}`)
+  }
+  return lines.join("\n")
+}
+
+/**
+ * Shows the errors and warnings of a preview or of opening a file, if there
+ * are any. Live previews don't show theirs, since they compile while typing.
+ */
+const onDiagnostics = (_filename: string, diagnostics: Diagnostic[]) => {
+  if (diagnostics.length > 0) {
+    const failed = diagnostics.some((d) => d.severity === "error")
+    showFailureMessage(diagnostics.map(formatDiagnostic).join("\n\n"), {
+      title: failed ? "Typst compilation failed!" : "Typst compilation warnings",
+      raw: true,
+    })
+  }
+}
+
+/**
+ * The filename the backend compiles a document as, which for untitled documents
+ * is a virtual file named after their id, so that their previews don't collide,
+ * even when closing other tabs moves them.
+ */
+const compiledFilename = (document: TyXDocument) =>
+  document.filename ?? (document.id ? `Untitled-${document.id}.tyx` : "")
+
 export const initializeBackend = () => {
   getVersion().then((v) => (version = v))
   listen<[string, string]>("open", (e) => onOpen(...e.payload))
   listen<[string]>("insertImage", (e) => onInsertImage(...e.payload))
   listen<[string]>("saveas", (e) => onSaveAs(...e.payload))
+  listen<[string, Diagnostic[]]>("diagnostics", (e) =>
+    onDiagnostics(...e.payload),
+  )
 
   getSettingsFromFile().then((settings) => {
     if (settings !== undefined) {
@@ -57,31 +126,70 @@ export const onPreview = async (open = false) => {
     return
   }
 
-  let content: string
-  try {
-    content = serialized_tyx_to_typst(JSON.stringify(document))
-  } catch (e: any) {
-    showFailureMessage(e.message)
-    return
-  }
-  const result: string = await invoke("preview", {
-    filename: document.filename ?? "",
-    content,
+  const diagnostics = await invoke<Diagnostic[]>("preview", {
+    filename: compiledFilename(document),
+    document: JSON.stringify(document),
     root: document.settings?.root ?? "",
     fontPaths: document.settings?.fontPaths ?? [],
+    pdfOptions: document.settings?.pdf ?? {},
     open: open ?? false,
   })
-  if (result) {
-    showFailureMessage(result.replace(/\n\n/g, "\n"), {
-      title: "Typst compilation failed!",
-      raw: true,
-    })
-  }
+  onDiagnostics(compiledFilename(document), diagnostics)
 
   document.dirty = false
   setLocalStorage("Open Documents", openDocuments)
 }
 
+export const closeSession = (document: TyXDocument | undefined) => {
+  if (document) {
+    invoke("closesession", { filename: compiledFilename(document) })
+  }
+}
+
+export const livePreview = async (full = false) => {
+  const openDocuments = getLocalStorage<TyXDocument[]>("Open Documents", [])
+  const currentDocument = getLocalStorage<number>("Current Document")
+  const document = openDocuments[currentDocument]
+  if (!document) {
+    return
+  }
+
+  await invoke("livepreview", {
+    filename: compiledFilename(document),
+    document: JSON.stringify(document),
+    root: document.settings?.root ?? "",
+    fontPaths: document.settings?.fontPaths ?? [],
+    full,
+  })
+}
+
+/** Calls the callback with the pages of each live preview, until unsubscribed. */
+export const onPreviewPages = (callback: (pages: PreviewPages) => void) => {
+  const unlisten = listen<PreviewPages>("pages", (e) => callback(e.payload))
+  return () => {
+    unlisten.then((f) => f())
+  }
+}
+
+/** Returns the path of the TyX node at the given point of a preview page, in points. */
+export const jumpToSource = (
+  filename: string,
+  page: number,
+  x: number,
+  y: number,
+) => invoke<string | null>("jumptosource", { filename, page, x, y })
+
+/** Returns the font families available to the current document. */
+export const listFonts = async () => {
+  const openDocuments = getLocalStorage<TyXDocument[]>("Open Documents", [])
+  const currentDocument = getLocalStorage<number>("Current Document")
+  const document = openDocuments[currentDocument]
+  return await invoke<FontFamily[]>("listfonts", {
+    filename: document ? compiledFilename(document) : "",
+    fontPaths: document?.settings?.fontPaths ?? [],
+  })
+}
+
 export const onSaveAs = (filename?: string) => {
   if (!filename) {
     invoke("saveas")
@@ -143,7 +251,7 @@ export const onOpen = (
   if (!includeFilename) {
     filename = undefined
   }
-  openDocuments.push({ ...parsedContent, filename })
+  openDocuments.push({ ...parsedContent, filename, id: crypto.randomUUID() })
   setLocalStorage("Open Documents", openDocuments)
   setLocalStorage("Current Document", openDocuments.length - 1)
 }
@@ -11,7 +11,12 @@ import { serialized_tyx_to_typst } from "../converters"
 import { TyXDocument, TyXSettings } from "../models"
 import { showFailureMessage } from "../utilities"
 import { getLocalStorage, setLocalStorage } from "../utilities/hooks"
-import { serializeDocument, Update } from "./base"
+import {
+  FontFamily,
+  PreviewPages,
+  serializeDocument,
+  Update,
+} from "./base"
 
 let compiler: TypstCompiler
 
@@ -42,10 +47,14 @@ export const onPreview = async (_open = false) => {
   const openDocuments = getLocalStorage<TyXDocument[]>("Open Documents", [])
   const currentDocument = getLocalStorage<number>("Current Document")
   const document = openDocuments[currentDocument]
-  compiler.addSource(
-    "/main.typ",
-    serialized_tyx_to_typst(JSON.stringify(document)),
-  )
+  let content: string
+  try {
+    content = serialized_tyx_to_typst(JSON.stringify(document))
+  } catch (e: any) {
+    showFailureMessage(e.message)
+    return
+  }
+  compiler.addSource("/main.typ", content)
   const result = await compiler.compile({
     format: "pdf",
     mainFilePath: "/main.typ",
@@ -152,6 +161,33 @@ export const open = (_filename?: string) => {
   input.click()
 }
 
+// eslint-disable-next-line @typescript-eslint/no-unused-vars
+export const livePreview = async (_full = false) => {}
+
+// eslint-disable-next-line @typescript-eslint/no-unused-vars
+export const onPreviewPages = (_callback: (pages: PreviewPages) => void) => {
+  return () => {}
+}
+
+export const jumpToSource = async (
+  // eslint-disable-next-line @typescript-eslint/no-unused-vars
+  _filename: string,
+  // eslint-disable-next-line @typescript-eslint/no-unused-vars
+  _page: number,
+  // eslint-disable-next-line @typescript-eslint/no-unused-vars
+  _x: number,
+  // eslint-disable-next-line @typescript-eslint/no-unused-vars
+  _y: number,
+): Promise<string | null> => null
+
+// eslint-disable-next-line @typescript-eslint/no-unused-vars
+export const closeSession = (
+  // eslint-disable-next-line @typescript-eslint/no-unused-vars
+  _document: TyXDocument | undefined,
+) => {}
+
+export const listFonts = async (): Promise<FontFamily[]> => []
+
 export const insertImage = () => {}
 
 export const onInsertImage = () => {}
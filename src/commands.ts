@@ -51,6 +51,7 @@ import {
   FILE_PREVIEW_COMMAND,
   FILE_SAVE_AS_COMMAND,
   FILE_SAVE_COMMAND,
+  TOGGLE_LIVE_PREVIEW_COMMAND,
   INSERT_CODE_BLOCK_COMMAND,
   INSERT_HEADING_COMMAND,
   INSERT_QUOTE_COMMAND,
@@ -104,6 +105,7 @@ const COMMANDS: Record<string, LexicalCommand<any>> = {
   fileExport: FILE_EXPORT_COMMAND,
   fileClose: FILE_CLOSE_COMMAND,
   filePreview: FILE_PREVIEW_COMMAND,
+  toggleLivePreview: TOGGLE_LIVE_PREVIEW_COMMAND,
   openSettings: OPEN_SETTINGS_COMMAND,
   openDocumentSettings: OPEN_DOCUMENT_SETTINGS_COMMAND,
   setFontSize: SET_FONT_SIZE_COMMAND,
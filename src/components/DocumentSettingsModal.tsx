@@ -6,6 +6,7 @@ import {
   Button,
   Checkbox,
   Fieldset,
+  MultiSelect,
   NumberInput,
   Select,
   TagsInput,
@@ -13,13 +14,18 @@ import {
   TextInput,
 } from "@mantine/core"
 import {
+  IconCertificate,
   IconColumns,
   IconDeviceFloppy,
   IconFileHorizontal,
+  IconFiles,
   IconFolder,
   IconIcons,
+  IconId,
   IconIndentIncrease,
   IconLanguage,
+  IconTypography,
+  IconUser,
 } from "@tabler/icons-react"
 import { useTranslation } from "react-i18next"
 import { TyXDocument, TyXDocumentSettings } from "../models"
@@ -740,6 +746,84 @@ const DocumentSettingsModal = () => {
           }
         />
       </Fieldset>
+      <Fieldset legend={t("pdfExport")} mt="xs">
+        <TextInput
+          label={t("title")}
+          leftSection={<IconTypography />}
+          value={currentSettings.title ?? ""}
+          onChange={(e) =>
+            setSettings({
+              ...currentSettings,
+              title: e.currentTarget.value || undefined,
+            })
+          }
+        />
+        <TextInput
+          mt="xs"
+          label={t("author")}
+          leftSection={<IconUser />}
+          value={currentSettings.author ?? ""}
+          onChange={(e) =>
+            setSettings({
+              ...currentSettings,
+              author: e.currentTarget.value || undefined,
+            })
+          }
+        />
+        <MultiSelect
+          mt="xs"
+          label={t("pdfStandards")}
+          leftSection={<IconCertificate />}
+          value={currentSettings.pdf?.standards ?? []}
+          onChange={(standards) =>
+            setSettings({
+              ...currentSettings,
+              pdf: { ...currentSettings.pdf, standards },
+            })
+          }
+          data={[
+            { label: "PDF 1.7", value: "1.7" },
+            { label: "PDF 2.0", value: "2.0" },
+            { label: "PDF/A-2b", value: "a-2b" },
+            { label: "PDF/A-2u", value: "a-2u" },
+            { label: "PDF/A-3b", value: "a-3b" },
+            { label: "PDF/A-3u", value: "a-3u" },
+            { label: "PDF/A-4", value: "a-4" },
+            { label: "PDF/UA-1", value: "ua-1" },
+          ]}
+        />
+        <TextInput
+          mt="xs"
+          label={t("pages")}
+          placeholder="1-3,5,8-"
+          leftSection={<IconFiles />}
+          value={currentSettings.pdf?.pages ?? ""}
+          onChange={(e) =>
+            setSettings({
+              ...currentSettings,
+              pdf: {
+                ...currentSettings.pdf,
+                pages: e.currentTarget.value || undefined,
+              },
+            })
+          }
+        />
+        <TextInput
+          mt="xs"
+          label={t("identifier")}
+          leftSection={<IconId />}
+          value={currentSettings.pdf?.identifier ?? ""}
+          onChange={(e) =>
+            setSettings({
+              ...currentSettings,
+              pdf: {
+                ...currentSettings.pdf,
+                identifier: e.currentTarget.value || undefined,
+              },
+            })
+          }
+        />
+      </Fieldset>
       <Button
         fullWidth
         mt="xs"
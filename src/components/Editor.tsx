@@ -29,6 +29,7 @@ import CurrentEditorPlugin from "./plugins/CurrentEditorPlugin"
 import FunctionCallPlugin from "./plugins/FunctionCallPlugin"
 import ImagePlugin from "./plugins/ImagePlugin"
 import KeyboardMapPlugin from "./plugins/KeyboardMapPlugin"
+import LivePreviewPlugin from "./plugins/LivePreviewPlugin"
 import MathPlugin from "./plugins/MathPlugin"
 import RemoveDefaultShortcutsPlugin from "./plugins/RemoveDefaultShortcutsPlugin"
 import RTLPlugin from "./plugins/RTLPlugin"
@@ -61,13 +62,16 @@ const Editor = () => {
     <LexicalComposer initialConfig={config}>
       <ToolbarPlugin />
 
-      <div style={{ flexGrow: 1, padding: 10, overflowY: "auto" }}>
-        <RichTextPlugin
-          contentEditable={
-            <ContentEditable aria-placeholder="" placeholder={<></>} />
-          }
-          ErrorBoundary={LexicalErrorBoundary}
-        />
+      <div style={{ display: "flex", flexGrow: 1, minHeight: 0 }}>
+        <div style={{ flex: 1, padding: 10, overflowY: "auto" }}>
+          <RichTextPlugin
+            contentEditable={
+              <ContentEditable aria-placeholder="" placeholder={<></>} />
+            }
+            ErrorBoundary={LexicalErrorBoundary}
+          />
+        </div>
+        <LivePreviewPlugin />
       </div>
       <HistoryPlugin externalHistoryState={historyState} />
       <HorizontalRulePlugin />
@@ -153,6 +153,27 @@ export const FunctionCallEditModal = ({
   )
 }
 
+/** Shows a function call as Typst code, or just its name if its parameters can't be converted. */
+const stringifyFunctionCall = (
+  name: string,
+  positionParameters: TyXValue[] | undefined,
+  namedParameters: Record<string, TyXValue> | undefined,
+) => {
+  if (!state.wasmInitialized) {
+    return ""
+  }
+  try {
+    return serialized_stringify_function(
+      name,
+      JSON.stringify(positionParameters),
+      JSON.stringify(namedParameters),
+      false,
+    ).replace("()", "")
+  } catch {
+    return name
+  }
+}
+
 export const FunctionCallEditor = ({
   name,
   contents,
@@ -196,7 +217,7 @@ export const FunctionCallEditor = ({
         }}
         onClick={() =>
           modals.open({
-            title: `Edit ${state.wasmInitialized ? serialized_stringify_function(name, JSON.stringify(positionParameters), JSON.stringify(namedParameters), false).replace("()", "") : ""}`,
+            title: `Edit ${stringifyFunctionCall(name, positionParameters, namedParameters)}`,
             children: (
               <FunctionCallEditModal
                 editor={editor}
@@ -209,14 +230,7 @@ export const FunctionCallEditor = ({
           })
         }
       >
-        {state.wasmInitialized
-          ? serialized_stringify_function(
-              name,
-              JSON.stringify(positionParameters),
-              JSON.stringify(namedParameters),
-              false,
-            ).replace("()", "")
-          : ""}
+        {stringifyFunctionCall(name, positionParameters, namedParameters)}
       </span>
       {Object.keys(contents).length !== 0 &&
         Object.keys(contents)
@@ -0,0 +1,134 @@
+/**
+ * @file A pane previewing the current document next to the editor, rendered by the backend as it changes.
+ */
+
+import { useLexicalComposerContext } from "@lexical/react/LexicalComposerContext"
+import { useDebouncedCallback } from "@mantine/hooks"
+import {
+  $getRoot,
+  $isElementNode,
+  COMMAND_PRIORITY_EDITOR,
+  LexicalEditor,
+  LexicalNode,
+} from "lexical"
+import { MouseEvent, useEffect, useMemo, useState } from "react"
+import { jumpToSource, livePreview, onPreviewPages } from "../../backend"
+import { DEFAULT_SERVER_DEBOUNCE_MILLISECONDS } from "../../models"
+import { getSettings } from "../../settings"
+import { TOGGLE_LIVE_PREVIEW_COMMAND } from "./tyxCommands"
+
+/** Selects the node at the given TyX path, such as `root/children/2/children/0`. */
+const selectPath = (editor: LexicalEditor, path: string) => {
+  editor.update(() => {
+    const segments = path.split("/")
+    if (segments[0] !== "root") {
+      return
+    }
+
+    let node: LexicalNode = $getRoot()
+    for (let i = 1; i + 1 < segments.length; i += 2) {
+      const child =
+        segments[i] === "children" && $isElementNode(node)
+          ? node.getChildren()[Number(segments[i + 1])]
+          : undefined
+      if (!child) {
+        break
+      }
+      node = child
+    }
+
+    node.selectStart()
+    editor
+      .getElementByKey(node.getKey())
+      ?.scrollIntoView({ block: "center", behavior: "smooth" })
+  })
+}
+
+const LivePreviewPlugin = () => {
+  const [editor] = useLexicalComposerContext()
+  const [open, setOpen] = useState(false)
+  const [filename, setFilename] = useState("")
+  const [pages, setPages] = useState<string[]>([])
+  const debounce = useMemo(
+    () => getSettings().serverDebounce ?? DEFAULT_SERVER_DEBOUNCE_MILLISECONDS,
+    [],
+  )
+  const debouncedPreview = useDebouncedCallback(livePreview, debounce)
+
+  useEffect(() => {
+    return editor.registerCommand(
+      TOGGLE_LIVE_PREVIEW_COMMAND,
+      () => {
+        setOpen((open) => !open)
+        return true
+      },
+      COMMAND_PRIORITY_EDITOR,
+    )
+  }, [editor])
+
+  useEffect(() => {
+    if (!open) {
+      return
+    }
+
+    const unsubscribe = onPreviewPages((update) => {
+      setFilename(update.filename)
+      setPages((pages) => {
+        const updated = pages.slice(0, update.count)
+        for (const [index, svg] of update.pages) {
+          updated[index] = svg
+        }
+        return updated
+      })
+    })
+    livePreview(true)
+    const unregister = editor.registerUpdateListener(() => debouncedPreview())
+    return () => {
+      unregister()
+      unsubscribe()
+      setPages([])
+    }
+  }, [open, editor])
+
+  const onClick = async (e: MouseEvent<HTMLDivElement>, page: number) => {
+    const svg = e.currentTarget.querySelector("svg")
+    if (!svg) {
+      return
+    }
+    const rect = svg.getBoundingClientRect()
+    const viewBox = svg.viewBox.baseVal
+    const x = ((e.clientX - rect.left) / rect.width) * viewBox.width
+    const y = ((e.clientY - rect.top) / rect.height) * viewBox.height
+    const path = await jumpToSource(filename, page, x, y)
+    if (path) {
+      selectPath(editor, path)
+    }
+  }
+
+  if (!open) {
+    return null
+  }
+
+  return (
+    <div
+      style={{
+        flex: 1,
+        overflowY: "auto",
+        padding: 10,
+        background: "var(--mantine-color-gray-light)",
+      }}
+    >
+      {pages.map((svg, index) => (
+        <div
+          key={index}
+          className="preview-page"
+          onClick={(e) => onClick(e, index)}
+          dangerouslySetInnerHTML={{ __html: svg }}
+          style={{ marginBottom: 10, background: "white", cursor: "pointer" }}
+        />
+      ))}
+    </div>
+  )
+}
+
+export default LivePreviewPlugin
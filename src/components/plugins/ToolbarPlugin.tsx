@@ -34,6 +34,7 @@ import {
   IconDeviceFloppy,
   IconEye,
   IconFileCode,
+  IconFileTypeHtml,
   IconFloatCenter,
   IconFunction,
   IconH1,
@@ -43,6 +44,7 @@ import {
   IconIndentDecrease,
   IconIndentIncrease,
   IconItalic,
+  IconLayoutSidebarRight,
   IconLineDotted,
   IconLink,
   IconList,
@@ -226,6 +228,9 @@ const ManagementControls = () => {
       <ToolbarControl label="Export to Typst" command="fileExport typst">
         <IconFileCode />
       </ToolbarControl>
+      <ToolbarControl label="Export to HTML" command="fileExport html">
+        <IconFileTypeHtml />
+      </ToolbarControl>
       <ToolbarControl
         label="Preview PDF"
         loading={loadingPreview}
@@ -233,6 +238,11 @@ const ManagementControls = () => {
       >
         <IconEye />
       </ToolbarControl>
+      {!isWeb && (
+        <ToolbarControl label="Toggle live preview" command="toggleLivePreview">
+          <IconLayoutSidebarRight />
+        </ToolbarControl>
+      )}
       {!isWeb && (
         <ToolbarControl
           label={
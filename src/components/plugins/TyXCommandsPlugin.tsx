@@ -20,7 +20,10 @@ import {
   save,
 } from "../../backend"
 import { onClose } from "../../backend/common"
-import { serialized_tyx_to_typst } from "../../converters"
+import {
+  serialized_tyx_to_html,
+  serialized_tyx_to_typst,
+} from "../../converters"
 import { TyXDocument } from "../../models"
 import {
   $getToolbarState,
@@ -51,6 +54,15 @@ import {
   SET_FONT_SIZE_COMMAND,
 } from "./tyxCommands"
 
+/** The formats documents can be exported to, by the export command's argument. */
+const EXPORTERS: Record<
+  string,
+  { extension: string; convert: (document: string) => string }
+> = {
+  typst: { extension: ".typ", convert: serialized_tyx_to_typst },
+  html: { extension: ".html", convert: serialized_tyx_to_html },
+}
+
 const TyXCommandsPlugin = () => {
   const [editor] = useLexicalComposerContext()
 
@@ -165,7 +177,8 @@ const TyXCommandsPlugin = () => {
       editor.registerCommand(
         FILE_EXPORT_COMMAND,
         (format) => {
-          if (format === "typst") {
+          const exporter = EXPORTERS[format]
+          if (exporter) {
             const openDocuments = getLocalStorage<TyXDocument[]>(
               "Open Documents",
               [],
@@ -174,14 +187,14 @@ const TyXCommandsPlugin = () => {
             const doc = openDocuments[currentDocument]
             const filename = (doc.filename ?? "Untitled.tyx").replace(
               ".tyx",
-              ".typ",
+              exporter.extension,
             )
             try {
-              save(filename, serialized_tyx_to_typst(JSON.stringify(doc))).then(
-                () => showSuccessMessage(`Document exported to ${filename}.`),
+              save(filename, exporter.convert(JSON.stringify(doc))).then(() =>
+                showSuccessMessage(`Document exported to ${filename}.`),
               )
-            } catch {
-              showFailureMessage("Document export failed!")
+            } catch (e: any) {
+              showFailureMessage(`Document export failed: ${e.message}`)
             }
           }
           return true
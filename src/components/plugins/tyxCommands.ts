@@ -17,6 +17,8 @@ export const FILE_SAVE_AS_COMMAND: LexicalCommand<void> = createCommand()
 export const FILE_CLOSE_COMMAND: LexicalCommand<number | undefined> =
   createCommand()
 export const FILE_PREVIEW_COMMAND: LexicalCommand<void> = createCommand()
+export const TOGGLE_LIVE_PREVIEW_COMMAND: LexicalCommand<void> =
+  createCommand()
 
 export const OPEN_SETTINGS_COMMAND: LexicalCommand<void> = createCommand()
 export const OPEN_DOCUMENT_SETTINGS_COMMAND: LexicalCommand<void> =
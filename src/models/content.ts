@@ -16,6 +16,14 @@ export const TyXDirection = TyXDirectionValue.nullable()
   .meta({ id: "TyXDirection" })
 export type TyXDirection = z.infer<typeof TyXDirection>
 
+export const TyXTextFormat = z
+  .int()
+  .describe(
+    "The formats of text as a bitmask of Lexical's text formats, e.g. 1 for bold, 2 for italic and 3 for both.",
+  )
+  .meta({ id: "TyXTextFormat" })
+export type TyXTextFormat = z.infer<typeof TyXTextFormat>
+
 export const TyXEditorState = z.looseObject({
   editorState: z.looseObject({
     get root() {
@@ -65,7 +73,7 @@ export const TyXTextNode = z
   .looseObject({
     type: z.literal("text"),
     text: z.string(),
-    format: z.int(),
+    format: TyXTextFormat,
     style: z.string().optional(),
   })
   .describe("A node describing text.")
@@ -90,6 +98,7 @@ export const TyXListItemNode = z
     get children() {
       return z.array(TyXNode)
     },
+    checked: z.boolean().optional(),
   })
   .describe("A node describing a list item.")
   .meta({ title: "TyXListItemNode" })
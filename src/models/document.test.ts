@@ -44,6 +44,17 @@ describe("TyXDocumentSettings", () => {
     const result = TyXDocumentSettings.parse({ columns: 2 })
     expect(result.columns).toBe(2)
   })
+
+  it("parses settings with metadata and PDF options", () => {
+    const result = TyXDocumentSettings.parse({
+      title: "Notes",
+      author: "Ada",
+      pdf: { standards: ["a-2b"], pages: "1-3", creationTimestamp: 0 },
+    })
+    expect(result.title).toBe("Notes")
+    expect(result.pdf?.standards).toEqual(["a-2b"])
+    expect(result.pdf?.creationTimestamp).toBe(0)
+  })
 })
 
 describe("TyXDocument", () => {
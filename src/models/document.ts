@@ -22,7 +22,34 @@ export const TyXCompilationOptions = z
   .meta({ id: "TyXCompilationOptions" })
 export type TyXCompilationOptions = z.infer<typeof TyXCompilationOptions>
 
+export const TyXPdfOptions = z
+  .object({
+    standards: z
+      .array(z.string())
+      .optional()
+      .describe(
+        "The PDF standards the document must conform to, e.g. 'a-2b' or 'ua-1'.",
+      ),
+    pages: z
+      .string()
+      .optional()
+      .describe("The pages to export, e.g. '1-3,5,8-'."),
+    creationTimestamp: z
+      .number()
+      .optional()
+      .describe("The creation time of the document, in seconds since 1970."),
+    identifier: z
+      .string()
+      .optional()
+      .describe("A stable identifier of the document across versions."),
+  })
+  .describe("Options for exporting the document to PDF.")
+  .meta({ id: "TyXPdfOptions" })
+export type TyXPdfOptions = z.infer<typeof TyXPdfOptions>
+
 export const TyXDocumentSettings = TyXCompilationOptions.extend({
+  title: z.string().optional().describe("The title of the document."),
+  author: z.string().optional().describe("The author of the document."),
   language: z.string().optional().describe("The language of the document."),
   paper: z.string().optional().describe("The paper size of the document."),
   flipped: z
@@ -44,6 +71,7 @@ export const TyXDocumentSettings = TyXCompilationOptions.extend({
     .record(z.string(), FunctionDefinition)
     .optional()
     .describe("Additional TyX function definitions."),
+  pdf: TyXPdfOptions.optional().describe("The document's PDF export options."),
 })
   .describe(
     "An object wrapping some common Typst document configuration options.",
@@ -71,6 +99,10 @@ export const TyXDocument = z
       .string()
       .optional()
       .describe("The filename of the document, unused."),
+    id: z
+      .string()
+      .optional()
+      .describe("A unique identifier of the open document, unused."),
     content: TyXDocumentContent.optional(),
     dirty: z
       .boolean()